@@ -0,0 +1,329 @@
+use std::fs::{copy, create_dir_all, read_dir, remove_dir_all, remove_file, rename};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+
+use crate::storage::{self, StorageError};
+
+// The files that make up the data. They are copied as is so every snapshot is
+// a data directory of its own.
+const FILES: [&str; 5] = [
+    "entrys.csv",
+    "archive.csv",
+    "batches.csv",
+    "timer.csv",
+    "hours.db",
+];
+
+// Snapshots are named by when they were made, the milliseconds keep them in
+// order when several are made in the same second
+const NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S%.3f";
+
+#[derive(Clone, Copy)]
+pub struct Retention {
+    // How many snapshots to keep
+    pub keep: usize,
+    // Snapshots older than this are removed, 0 keeps them regardless of age
+    pub max_age_days: u32,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            keep: 20,
+            max_age_days: 0,
+        }
+    }
+}
+
+pub struct Snapshot {
+    pub name: String,
+    pub path: PathBuf,
+    created: Option<NaiveDateTime>,
+}
+
+pub struct Summary {
+    pub entrys: usize,
+    pub minutes: i64,
+    pub archived: usize,
+    pub archived_minutes: i64,
+}
+
+fn backups_dir(dir: &Path) -> PathBuf {
+    dir.join("backups")
+}
+
+// Copies the data files into a new timestamped folder under backups/ and
+// removes the snapshots the retention no longer covers.
+pub fn snapshot(dir: &Path, retention: Retention) -> Result<(), io::Error> {
+    create(dir)?;
+    prune(dir, retention)
+}
+
+fn create(dir: &Path) -> Result<(), io::Error> {
+    let files: Vec<_> = FILES
+        .iter()
+        .filter(|file| dir.join(file).exists())
+        .collect();
+
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let backups = backups_dir(dir);
+    let stamp = Local::now().format(NAME_FORMAT).to_string();
+
+    let mut path = backups.join(&stamp);
+    let mut n = 1;
+
+    while path.exists() {
+        path = backups.join(format!("{stamp}-{n}"));
+        n += 1;
+    }
+
+    // Copy into a hidden folder first so a half made snapshot is never listed
+    let tmp = backups.join(".snapshot.tmp");
+
+    if tmp.exists() {
+        remove_dir_all(&tmp)?;
+    }
+
+    create_dir_all(&tmp)?;
+
+    for file in files {
+        copy(dir.join(file), tmp.join(file))?;
+    }
+
+    rename(tmp, path)
+}
+
+// Snapshots newest first
+pub fn list(dir: &Path) -> Result<Vec<Snapshot>, io::Error> {
+    let backups = backups_dir(dir);
+
+    if !backups.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+
+    for item in read_dir(backups)? {
+        let item = item?;
+        let name = item.file_name().to_string_lossy().into_owned();
+
+        if name.starts_with('.') || !item.file_type()?.is_dir() {
+            continue;
+        }
+
+        let created = name
+            .get(..23)
+            .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, NAME_FORMAT).ok());
+
+        snapshots.push(Snapshot {
+            name,
+            path: item.path(),
+            created,
+        });
+    }
+
+    snapshots.sort_by(|a, b| order(&b.name).cmp(&order(&a.name)));
+
+    Ok(snapshots)
+}
+
+// The names sort in the order they were made, apart from the number added when
+// the stamp was taken so "-10" comes after "-2"
+fn order(name: &str) -> (&str, u32) {
+    match name.get(23..).and_then(|rest| rest.strip_prefix('-')) {
+        Some(n) => (&name[..23], n.parse().unwrap_or(0)),
+        None => (name, 0),
+    }
+}
+
+// The newest snapshot is always kept no matter what the retention says
+fn prune(dir: &Path, retention: Retention) -> Result<(), io::Error> {
+    let oldest = Local::now().naive_local() - TimeDelta::days(retention.max_age_days.into());
+
+    for (i, snapshot) in list(dir)?.into_iter().enumerate().skip(1) {
+        let too_old =
+            retention.max_age_days != 0 && snapshot.created.is_some_and(|created| created < oldest);
+
+        if i >= retention.keep || too_old {
+            remove_dir_all(snapshot.path)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn summarize(snapshot: &Snapshot) -> Result<Summary, StorageError> {
+    let storage = storage::open_snapshot(&snapshot.path)?;
+
+    let mut entrys = Vec::new();
+    let mut minutes = 0;
+    storage.read_entrys(&mut entrys, &mut minutes)?;
+
+    let mut archive = Vec::new();
+    let mut archived_minutes = 0;
+    storage.read_archive(&mut archive, &mut archived_minutes)?;
+
+    Ok(Summary {
+        entrys: entrys.len(),
+        minutes,
+        archived: archive.iter().map(|batch| batch.entrys.len()).sum(),
+        archived_minutes,
+    })
+}
+
+// Puts the files from a snapshot back in the data directory, removing any that
+// didn't exist then. The current files are snapshotted first so it can be undone.
+// Any open database has to be closed before this.
+pub fn restore(dir: &Path, snapshot: &Path, retention: Retention) -> Result<(), io::Error> {
+    create(dir)?;
+
+    for file in FILES {
+        let from = snapshot.join(file);
+        let to = dir.join(file);
+
+        if from.exists() {
+            let tmp = dir.join(format!("{file}.tmp"));
+            copy(from, &tmp)?;
+            rename(tmp, to)?;
+        } else if to.exists() {
+            remove_file(to)?;
+        }
+    }
+
+    prune(dir, retention)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hours-test-{}", Uuid::new_v4()));
+        create_dir_all(&dir).unwrap();
+        write(dir.join("entrys.csv"), "now").unwrap();
+        dir
+    }
+
+    // A snapshot made some time ago
+    fn made(dir: &Path, ago: TimeDelta) -> String {
+        let name = (Local::now().naive_local() - ago)
+            .format(NAME_FORMAT)
+            .to_string();
+        create_dir_all(backups_dir(dir).join(&name)).unwrap();
+        name
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        list(dir).unwrap().into_iter().map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest() {
+        let dir = data_dir();
+        let made: Vec<_> = (1..=5)
+            .map(|hours| made(&dir, TimeDelta::hours(hours)))
+            .collect();
+
+        let retention = Retention {
+            keep: 3,
+            max_age_days: 0,
+        };
+        prune(&dir, retention).unwrap();
+        assert_eq!(names(&dir), made[..3]);
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn removes_old_snapshots() {
+        let dir = data_dir();
+        let made: Vec<_> = [1, 10, 40, 50]
+            .into_iter()
+            .map(|days| made(&dir, TimeDelta::days(days)))
+            .collect();
+
+        let retention = Retention {
+            keep: 20,
+            max_age_days: 30,
+        };
+        prune(&dir, retention).unwrap();
+        assert_eq!(names(&dir), made[..2]);
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn always_keeps_one() {
+        let dir = data_dir();
+        let made: Vec<_> = [40, 50]
+            .into_iter()
+            .map(|days| made(&dir, TimeDelta::days(days)))
+            .collect();
+
+        let retention = Retention {
+            keep: 0,
+            max_age_days: 30,
+        };
+        prune(&dir, retention).unwrap();
+        assert_eq!(names(&dir), made[..1]);
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sorts_same_stamp_by_number() {
+        let dir = data_dir();
+        let stamp = made(&dir, TimeDelta::zero());
+        let earlier = made(&dir, TimeDelta::seconds(1));
+
+        for n in [1, 2, 10] {
+            create_dir_all(backups_dir(&dir).join(format!("{stamp}-{n}"))).unwrap();
+        }
+
+        assert_eq!(
+            names(&dir),
+            [
+                format!("{stamp}-10"),
+                format!("{stamp}-2"),
+                format!("{stamp}-1"),
+                stamp,
+                earlier,
+            ]
+        );
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restores_a_snapshot() {
+        let dir = data_dir();
+        snapshot(&dir, Retention::default()).unwrap();
+        let old = list(&dir).unwrap().remove(0);
+
+        write(dir.join("entrys.csv"), "later").unwrap();
+        write(dir.join("timer.csv"), "running").unwrap();
+
+        restore(&dir, &old.path, Retention::default()).unwrap();
+
+        assert_eq!(read_to_string(dir.join("entrys.csv")).unwrap(), "now");
+        assert!(!dir.join("timer.csv").exists());
+
+        // What was there before is a snapshot of its own
+        let snapshots = list(&dir).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        let undo = &snapshots[0].path;
+        assert_eq!(read_to_string(undo.join("entrys.csv")).unwrap(), "later");
+        assert_eq!(read_to_string(undo.join("timer.csv")).unwrap(), "running");
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::NaiveDateTime;
+
+use crate::entry::Entry;
+use crate::rounding::Rounding;
+
+// A group of entrys archived together by "Archive all"
+#[derive(Clone)]
+pub struct Batch {
+    pub id: u64,
+    // None for batches archived before the time was recorded
+    pub archived_at: Option<NaiveDateTime>,
+    pub label: String,
+    pub count: usize,
+    pub minutes: i64,
+    // What was billed as rounded when it was archived, None for batches
+    // archived before that was recorded
+    pub billed_minutes: Option<i64>,
+    pub entrys: Vec<Entry>,
+}
+
+impl Batch {
+    pub fn new(
+        id: u64,
+        archived_at: Option<NaiveDateTime>,
+        label: String,
+        billed_minutes: Option<i64>,
+        entrys: Vec<Entry>,
+    ) -> Self {
+        Batch {
+            id,
+            archived_at,
+            label,
+            count: entrys.len(),
+            minutes: entrys.iter().map(|entry| entry.minutes).sum(),
+            billed_minutes,
+            entrys,
+        }
+    }
+
+    // As billed when archived so later changes to rounding don't alter it,
+    // older batches are rounded as set now
+    pub fn billed(&self, rounding: &Rounding) -> i64 {
+        self.billed_minutes
+            .unwrap_or_else(|| rounding.total(&self.entrys))
+    }
+
+    // The label, or the range of dates covered when there isn't one
+    pub fn title(&self) -> String {
+        if !self.label.is_empty() {
+            return self.label.clone();
+        }
+
+        let first = self.entrys.iter().map(|entry| entry.date).min();
+        let last = self.entrys.iter().map(|entry| entry.date).max();
+
+        match (first, last) {
+            (Some(first), Some(last)) if first != last => format!("{first}..{last}"),
+            (Some(first), _) => first.to_string(),
+            _ => String::new(),
+        }
+    }
+}
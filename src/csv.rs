@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+use std::fs::{rename, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Write one record, quoting any field that contains a comma, quote or line break (RFC 4180).
+pub fn write_record<W: Write, S: AsRef<str>>(w: &mut W, fields: &[S]) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i != 0 {
+            w.write_all(b",")?;
+        }
+        w.write_all(escape(field.as_ref()).as_bytes())?;
+    }
+
+    w.write_all(b"\r\n")
+}
+
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Parse every record in `text`, skipping blank lines.
+///
+/// A quote is only special at the start of a field. Files from before fields
+/// were quoted are read with `parse_unquoted` instead.
+pub fn parse(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+
+    let mut field_start = true;
+    let mut in_quotes = false;
+
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c != '"' {
+                field.push(c);
+            } else if chars.peek() == Some(&'"') {
+                chars.next();
+                field.push('"');
+            } else {
+                in_quotes = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field_start => {
+                in_quotes = true;
+                field_start = false;
+            }
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                field_start = true;
+            }
+            '\r' | '\n' => {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+
+                record.push(std::mem::take(&mut field));
+                field_start = true;
+
+                // A lone empty field is just a blank line
+                if record.len() == 1 && record[0].is_empty() {
+                    record.clear();
+                } else {
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            _ => {
+                field.push(c);
+                field_start = false;
+            }
+        }
+    }
+
+    if !field_start || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+/// Split every line of `text` on commas, skipping blank lines.
+///
+/// For files written before fields were quoted, where a quote is just part of
+/// the text. A description with commas comes out over several fields.
+pub fn parse_unquoted(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split(',').map(str::to_string).collect())
+        .collect()
+}
+
+/// Write the records to a temporary file and rename it over `path`, so a
+/// crash part way through never leaves a half written file.
+pub fn replace_records<R, S>(path: &Path, records: &[R]) -> io::Result<()>
+where
+    R: AsRef<[S]>,
+    S: AsRef<str>,
+{
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    let mut file = File::create(&tmp)?;
+
+    for record in records {
+        write_record(&mut file, record.as_ref())?;
+    }
+
+    file.sync_all()?;
+    rename(&tmp, path)?;
+
+    sync_dir(path)
+}
+
+/// Make a rename or removal in the file's directory survive a power loss.
+#[cfg(unix)]
+pub fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+/// Windows has no way to open a directory for syncing, renames are flushed
+/// with the file.
+#[cfg(not(unix))]
+pub fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Records = &'static [&'static [&'static str]];
+
+    #[test]
+    fn round_trips_fields() {
+        let cases: [&[&str]; 7] = [
+            &["plain", "fields"],
+            &["with, commas", "b"],
+            &["\"quoted\" and \"\"doubled\"\"", "x"],
+            &["line\nbreak", "crlf\r\nbreak", "lone\rreturn"],
+            &["", "", ""],
+            &["trailing,", ",leading"],
+            &["\"", "\"\""],
+        ];
+
+        for fields in cases {
+            let mut bytes = Vec::new();
+            write_record(&mut bytes, fields).unwrap();
+            let text = String::from_utf8(bytes).unwrap();
+
+            assert_eq!(parse(&text), [fields], "{text:?}");
+        }
+    }
+
+    #[test]
+    fn parses_records() {
+        let two: Records = &[&["a", "b"], &["c", "d"]];
+
+        let cases: [(&str, Records); 8] = [
+            ("a,b\nc,d\n", two),
+            ("a,b\r\nc,d\r\n", two),
+            ("a,b\r\nc,d", two),
+            ("\na,b\n\n\r\nc,d\n\n", two),
+            ("\"a\nb\",c\r\n", &[&["a\nb", "c"]]),
+            ("\"a\r\nb\",c\n", &[&["a\r\nb", "c"]]),
+            ("\"say \"\"hi\"\"\",x", &[&["say \"hi\"", "x"]]),
+            ("a,,\n", &[&["a", "", ""]]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse(text), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn splits_unquoted_lines() {
+        let cases: [(&str, Records); 3] = [
+            // A description that starts with a quote doesn't swallow the
+            // lines after it
+            (
+                "2024-01-01,\"Hotfix,9:00am,10:00am,1\n2024-01-02,Other,9am,10am,1\n",
+                &[
+                    &["2024-01-01", "\"Hotfix", "9:00am", "10:00am", "1"],
+                    &["2024-01-02", "Other", "9am", "10am", "1"],
+                ],
+            ),
+            (
+                "a,say \"hi\",b\r\n\r\nc,d",
+                &[&["a", "say \"hi\"", "b"], &["c", "d"]],
+            ),
+            ("-\n\n-\n", &[&["-"], &["-"]]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse_unquoted(text), expected, "{text:?}");
+        }
+    }
+}
@@ -1,8 +1,7 @@
 use std::hash::Hash;
 
-pub use chrono::offset::{FixedOffset, Local, Utc};
+pub use chrono::offset::Local;
 use chrono::prelude::*;
-pub use chrono::DateTime;
 use chrono::Duration;
 use eframe::egui;
 use eframe::egui::{Area, DragValue, Frame, Id, Key, Order, Response, RichText, Ui, Widget};
@@ -139,12 +138,12 @@ impl<'a> Widget for DatePicker<'a> {
                 })
                 .response;
 
-            if !button_response.clicked() {
-                if ui.input(|i| i.key_pressed(Key::Escape)) || area_response.clicked_elsewhere() {
-                    ui.memory_mut(|mem| {
-                        mem.toggle_popup(self.id);
-                    });
-                }
+            if !button_response.clicked()
+                && (ui.input(|i| i.key_pressed(Key::Escape)) || area_response.clicked_elsewhere())
+            {
+                ui.memory_mut(|mem| {
+                    mem.toggle_popup(self.id);
+                });
             }
         }
 
@@ -1,57 +1,214 @@
 use std::cmp::Ordering;
 
+use chrono::{DateTime, Days, NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::utils::instant;
+
+#[derive(Clone)]
 pub struct Entry {
-    pub date: String,
+    // Stays the same for as long as the entry exists, even when archived
+    pub id: Uuid,
+    pub date: NaiveDate,
     pub description: String,
-    pub start: String,
-    pub end: String,
-    pub hours: f64,
+    // Entrys made from just a duration have no start or end
+    pub start: Option<NaiveTime>,
+    pub end: Option<NaiveTime>,
+    // The day `end` is on, the same as `date` unless it goes past midnight
+    pub end_date: NaiveDate,
+    // The zone the times were written in
+    pub zone: Tz,
+    // Minutes worked, not counting the unpaid break
+    pub minutes: i64,
+    pub break_minutes: i64,
+    // What is billed after rounding, kept as it was when the entry was saved
+    pub billed_minutes: i64,
 }
 
 impl Entry {
-    pub fn new(date: String, description: String, start: String, end: String, hours: f64) -> Self {
+    pub fn new(
+        date: NaiveDate,
+        description: String,
+        start: Option<NaiveTime>,
+        end: Option<NaiveTime>,
+        end_date: NaiveDate,
+        zone: Tz,
+        minutes: i64,
+    ) -> Self {
         Entry {
+            id: Uuid::new_v4(),
             date,
             description,
             start,
             end,
-            hours,
+            end_date,
+            zone,
+            minutes,
+            break_minutes: 0,
+            billed_minutes: minutes,
         }
     }
-}
 
-fn get_ymd(date: &str) -> (u16, u8, u8) {
-    let mut values = date.split('-');
+    // Minutes from start to end, breaks included
+    pub fn gross_minutes(&self) -> i64 {
+        self.minutes + self.break_minutes
+    }
 
-    // It is safe to just unwrap but the entrys csv could be edited to cause a crash
-    if let (Some(y), Some(m), Some(d)) = (values.next(), values.next(), values.next()) {
-        return (
-            y.parse().unwrap_or(0),
-            m.parse().unwrap_or(0),
-            d.parse().unwrap_or(0),
-        );
+    pub fn starts_at(&self) -> Option<DateTime<Tz>> {
+        instant(self.zone, self.date.and_time(self.start?))
     }
 
-    (0, 0, 0)
-}
+    pub fn ends_at(&self) -> Option<DateTime<Tz>> {
+        instant(self.zone, self.end_date.and_time(self.end?))
+    }
+
+    // The same entry with its dates and times as they read in another zone
+    pub fn in_zone(&self, zone: Tz) -> Entry {
+        let (Some(start), Some(end)) = (self.starts_at(), self.ends_at()) else {
+            return self.clone();
+        };
 
-impl Ord for Entry {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Reversed because I wanted reverse ordering
-        get_ymd(&other.date).cmp(&get_ymd(&self.date))
+        let (start, end) = (start.with_timezone(&zone), end.with_timezone(&zone));
+
+        Entry {
+            date: start.date_naive(),
+            start: Some(start.time()),
+            end: Some(end.time()),
+            end_date: end.date_naive(),
+            zone,
+            ..self.clone()
+        }
     }
-}
 
-impl PartialOrd for Entry {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    // How many days after the start it ends
+    pub fn days_later(&self) -> i64 {
+        (self.end_date - self.date).num_days()
+    }
+
+    // The minutes worked on each calendar day, for splitting entrys that go
+    // past midnight. Entrys without times count entirely on their date.
+    // The days are those of the entry's own zone and the break is shared
+    // between them by how long each is.
+    pub fn minutes_by_day(&self) -> Vec<(NaiveDate, i64)> {
+        let (Some(mut from), Some(to)) = (self.starts_at(), self.ends_at()) else {
+            return vec![(self.date, self.minutes)];
+        };
+
+        let mut days = Vec::new();
+
+        while from < to {
+            let day = from.date_naive();
+            let midnight = instant(self.zone, (day + Days::new(1)).and_time(NaiveTime::MIN));
+            let until = midnight.map_or(to, |midnight| midnight.min(to));
+
+            days.push((day, (until - from).num_minutes()));
+            from = until;
+        }
+
+        if days.is_empty() {
+            return vec![(self.date, self.minutes)];
+        }
+
+        let gross = self.gross_minutes().max(1);
+        let mut left = self.minutes;
+
+        for (i, (_, minutes)) in days.iter_mut().enumerate().rev() {
+            // The first day gets what rounding left over
+            if i == 0 {
+                *minutes = left;
+            } else {
+                *minutes = *minutes * self.minutes / gross;
+                left -= *minutes;
+            }
+        }
+
+        days
+    }
+
+    // Newest first, for sorting with `sort_by`
+    pub fn by_date(a: &Entry, b: &Entry) -> Ordering {
+        b.date.cmp(&a.date)
     }
 }
 
+// Entrys are the same entry when their ids match, whatever their fields are
 impl PartialEq for Entry {
     fn eq(&self, other: &Self) -> bool {
-        self.date == other.date
+        self.id == other.id
     }
 }
 
 impl Eq for Entry {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_minutes_by_day() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let time = |hour: u32| NaiveTime::from_hms_opt(hour, 0, 0);
+
+        let entry = |zone: &str, day: u32, start: u32, end_day: u32, end: u32, break_minutes| {
+            let zone: Tz = zone.parse().unwrap();
+            let gross = (instant(zone, date(end_day).and_time(time(end).unwrap())).unwrap()
+                - instant(zone, date(day).and_time(time(start).unwrap())).unwrap())
+            .num_minutes();
+
+            Entry {
+                break_minutes,
+                ..Entry::new(
+                    date(day),
+                    String::new(),
+                    time(start),
+                    time(end),
+                    date(end_day),
+                    zone,
+                    gross - break_minutes,
+                )
+            }
+        };
+
+        let cases = [
+            (entry("UTC", 4, 9, 4, 17, 0), vec![(4, 480)]),
+            // Overnight
+            (entry("UTC", 4, 22, 5, 2, 0), vec![(4, 120), (5, 120)]),
+            // Over more than one midnight
+            (
+                entry("UTC", 4, 20, 6, 4, 0),
+                vec![(4, 240), (5, 1440), (6, 240)],
+            ),
+            // Clocks go forward an hour at 2:00 on the 31st in Berlin
+            (
+                entry("Europe/Berlin", 30, 22, 31, 4, 0),
+                vec![(30, 120), (31, 180)],
+            ),
+            // The break is shared by how long each day is
+            (entry("UTC", 4, 22, 5, 2, 60), vec![(4, 90), (5, 90)]),
+            (entry("UTC", 4, 23, 5, 2, 30), vec![(4, 50), (5, 100)]),
+            // Just a duration counts on its date
+            (
+                Entry::new(date(4), String::new(), None, None, date(4), Tz::UTC, 90),
+                vec![(4, 90)],
+            ),
+        ];
+
+        for (entry, expected) in cases {
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|(day, minutes)| (date(day), minutes))
+                .collect();
+
+            assert_eq!(
+                entry.minutes_by_day(),
+                expected,
+                "{} {:?} to {} {:?}",
+                entry.date,
+                entry.start,
+                entry.end_date,
+                entry.end
+            );
+        }
+    }
+}
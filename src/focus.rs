@@ -0,0 +1,54 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::timer::Timer;
+
+// Work and breaks of set lengths one after the other, like 25 and 5 minutes.
+// Work runs on the timer so each interval is saved and logged like any other
+// entry, breaks are never logged.
+pub struct Focus {
+    pub description: String,
+    pub work_minutes: i64,
+    pub break_minutes: i64,
+    pub phase: Phase,
+    // Work intervals logged so far
+    pub done: u32,
+    // When the phase last changed, to draw attention to it for a moment
+    pub changed: Instant,
+}
+
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Work,
+    // Until when
+    Break(DateTime<Utc>),
+}
+
+impl Focus {
+    pub fn new(description: String, work_minutes: i64, break_minutes: i64) -> Self {
+        Focus {
+            description,
+            work_minutes,
+            break_minutes,
+            phase: Phase::Work,
+            done: 0,
+            changed: Instant::now(),
+        }
+    }
+
+    // When the work or break is over. None if working without a timer, which
+    // means it was stopped or cancelled.
+    pub fn ends(&self, timer: Option<&Timer>) -> Option<DateTime<Utc>> {
+        match self.phase {
+            Phase::Work => timer.map(|timer| timer.started + Duration::minutes(self.work_minutes)),
+            Phase::Break(until) => Some(until),
+        }
+    }
+
+    // On and off for a few seconds after the phase changes
+    pub fn flashing(&self) -> bool {
+        let since = self.changed.elapsed();
+        since.as_secs() < 3 && since.as_millis() % 500 < 250
+    }
+}
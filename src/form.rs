@@ -0,0 +1,405 @@
+use std::fmt;
+
+use chrono::{Days, Duration, NaiveDate, NaiveTime, Timelike};
+use eframe::egui::{self, RichText, Stroke};
+
+use crate::datepicker::DatePicker;
+use crate::entry::Entry;
+use crate::utils::{
+    format_hours, has_meridiem, local_zone, minutes_in, parse_duration, parse_time, DurationError,
+    TimeDefaults, TimeError,
+};
+
+// What has been typed for an entry, shared by the input row and the edit window
+pub struct EntryForm {
+    pub date: NaiveDate,
+    pub description: String,
+    pub start_time: String,
+    pub end_time: String,
+    // Days after `date` that the end time is on. An end before the start is
+    // always taken as at least the next day.
+    pub end_days: u64,
+    pub duration: String,
+    // The unpaid break, empty when there wasn't one
+    pub break_time: String,
+    // An IANA zone name like "Europe/London", empty for the current zone
+    pub zone: String,
+    // Set once adding or saving has been tried, until then nothing is said
+    // about what hasn't been filled in yet
+    pub tried: bool,
+}
+
+// The inputs of the form that an error can be shown beside
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Start,
+    End,
+    Break,
+    Duration,
+    Zone,
+}
+
+// Why the form doesn't make an entry
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FormError {
+    Time(Field, TimeError),
+    Duration(Field, DurationError),
+    // Like "9am - 5", where the end reads as 05:00 but 5pm was likely meant
+    MissingMeridiem(NaiveTime),
+    SameTimes,
+    BreakTooLong,
+    UnknownZone,
+    OutOfRange,
+}
+
+impl FormError {
+    pub fn field(&self) -> Field {
+        match self {
+            FormError::Time(field, _) | FormError::Duration(field, _) => *field,
+            FormError::MissingMeridiem(_) | FormError::SameTimes | FormError::OutOfRange => {
+                Field::End
+            }
+            FormError::BreakTooLong => Field::Break,
+            FormError::UnknownZone => Field::Zone,
+        }
+    }
+
+    // Errors for what is still to be filled in
+    fn is_blank(&self) -> bool {
+        matches!(
+            self,
+            FormError::Time(_, TimeError::Empty) | FormError::Duration(_, DurationError::Empty)
+        )
+    }
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormError::Duration(Field::Duration, DurationError::Empty) => {
+                f.write_str("Needs a start and end or a duration")
+            }
+            FormError::Time(_, e) => e.fmt(f),
+            FormError::Duration(_, e) => e.fmt(f),
+            FormError::MissingMeridiem(end) => {
+                write!(f, "Reads as {}, add am or pm", end.format("%H:%M"))
+            }
+            FormError::SameTimes => f.write_str("Ends when it starts"),
+            FormError::BreakTooLong => f.write_str("The break is as long as the work"),
+            FormError::UnknownZone => f.write_str("Not a time zone, try Europe/London"),
+            FormError::OutOfRange => f.write_str("Ends too far away"),
+        }
+    }
+}
+
+impl EntryForm {
+    pub fn new(date: NaiveDate) -> Self {
+        EntryForm {
+            date,
+            description: String::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            end_days: 0,
+            duration: String::new(),
+            break_time: String::new(),
+            zone: String::new(),
+            tried: false,
+        }
+    }
+
+    pub fn from_entry(entry: &Entry) -> Self {
+        let times = entry.start.is_some() || entry.end.is_some();
+
+        EntryForm {
+            date: entry.date,
+            description: entry.description.clone(),
+            start_time: format_time(entry.start),
+            end_time: format_time(entry.end),
+            end_days: entry.days_later().try_into().unwrap_or(0),
+            duration: if times {
+                String::new()
+            } else {
+                format_duration(entry.gross_minutes())
+            },
+            break_time: if entry.break_minutes > 0 {
+                format_duration(entry.break_minutes)
+            } else {
+                String::new()
+            },
+            zone: if entry.zone == local_zone() {
+                String::new()
+            } else {
+                entry.zone.name().to_string()
+            },
+            tried: false,
+        }
+    }
+
+    // Empties everything but the date for the next entry
+    pub fn clear(&mut self) {
+        *self = EntryForm::new(self.date);
+    }
+
+    // A new entry from a start and end time, or from the duration when
+    // neither time is given. The break has to be shorter than either.
+    pub fn entry(&self, times: TimeDefaults) -> Result<Entry, FormError> {
+        let description = self.description.clone();
+
+        // A bare whole number is minutes here rather than hours, a break of
+        // 30 hours is never meant
+        let break_minutes = match self.break_time.trim() {
+            "" => 0,
+            break_time if break_time.bytes().all(|b| b.is_ascii_digit()) => {
+                parse_duration(&format!("{break_time}m"))
+                    .map_err(|e| FormError::Duration(Field::Break, e))?
+            }
+            break_time => {
+                parse_duration(break_time).map_err(|e| FormError::Duration(Field::Break, e))?
+            }
+        };
+
+        let zone = match self.zone.trim() {
+            "" => local_zone(),
+            zone => zone.parse().map_err(|_| FormError::UnknownZone)?,
+        };
+
+        if self.start_time.trim().is_empty() && self.end_time.trim().is_empty() {
+            let minutes = parse_duration(&self.duration)
+                .map_err(|e| FormError::Duration(Field::Duration, e))?;
+
+            if break_minutes >= minutes {
+                return Err(FormError::BreakTooLong);
+            }
+
+            return Ok(Entry {
+                break_minutes,
+                ..Entry::new(
+                    self.date,
+                    description,
+                    None,
+                    None,
+                    self.date,
+                    zone,
+                    minutes - break_minutes,
+                )
+            });
+        }
+
+        let start =
+            parse_time(&self.start_time, times).map_err(|e| FormError::Time(Field::Start, e))?;
+        let end = parse_time(&self.end_time, times).map_err(|e| FormError::Time(Field::End, e))?;
+
+        let mut days = self.end_days;
+
+        if days == 0 {
+            if end == start {
+                return Err(FormError::SameTimes);
+            }
+
+            // Only when the start had one, 24 hour times never need it
+            let meridiem = has_meridiem(&self.start_time) && !has_meridiem(&self.end_time);
+
+            if meridiem && end < start && end.hour() < 12 && end + Duration::hours(12) > start {
+                return Err(FormError::MissingMeridiem(end));
+            }
+        }
+
+        if end < start {
+            days = days.max(1);
+        }
+
+        let end_date = self
+            .date
+            .checked_add_days(Days::new(days))
+            .ok_or(FormError::OutOfRange)?;
+        let minutes = minutes_in(zone, self.date.and_time(start), end_date.and_time(end))
+            .ok_or(FormError::OutOfRange)?;
+
+        if break_minutes >= minutes {
+            return Err(FormError::BreakTooLong);
+        }
+
+        Ok(Entry {
+            break_minutes,
+            ..Entry::new(
+                self.date,
+                description,
+                Some(start),
+                Some(end),
+                end_date,
+                zone,
+                minutes - break_minutes,
+            )
+        })
+    }
+
+    // The date, times and duration on one line, with what is wrong beside the
+    // input it is about and the hours it comes to once it is right
+    pub fn times(&mut self, ui: &mut egui::Ui, id: &str, times: TimeDefaults) {
+        let result = self.entry(times);
+
+        let error = match &result {
+            Err(e) if self.tried || !e.is_blank() => Some(*e),
+            _ => None,
+        };
+        let error = |field| error.filter(|e| e.field() == field);
+
+        ui.add(DatePicker::new(id, &mut self.date));
+
+        let mut changed = false;
+
+        changed |= input(
+            ui,
+            &mut self.start_time,
+            "1:30pm",
+            60.0,
+            error(Field::Start),
+        )
+        .changed();
+
+        ui.label(RichText::new("-").size(18.0));
+
+        changed |= input(ui, &mut self.end_time, "10:30pm", 60.0, error(Field::End)).changed();
+
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut self.end_days)
+                    .range(0..=7)
+                    .prefix("+")
+                    .suffix("d"),
+            )
+            .on_hover_text("Days after the date that it ends")
+            .changed();
+
+        changed |= input(ui, &mut self.break_time, "break", 50.0, error(Field::Break))
+            .on_hover_text("Unpaid break, like 30m")
+            .changed();
+
+        ui.label("or");
+
+        changed |= input(
+            ui,
+            &mut self.duration,
+            "2h30m",
+            60.0,
+            error(Field::Duration),
+        )
+        .changed();
+
+        changed |= input(
+            ui,
+            &mut self.zone,
+            local_zone().name(),
+            110.0,
+            error(Field::Zone),
+        )
+        .on_hover_text("The time zone, like Europe/London. Empty is the current one.")
+        .changed();
+
+        if let Ok(entry) = &result {
+            let preview = if entry.break_minutes > 0 {
+                format!(
+                    "= {} hours ({} with the break)",
+                    format_hours(entry.minutes),
+                    format_hours(entry.gross_minutes())
+                )
+            } else {
+                format!("= {} hours", format_hours(entry.minutes))
+            };
+
+            ui.weak(preview);
+        }
+
+        // Check again with what was just typed
+        if changed {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+// A text input outlined in red with the error after it when there is one
+fn input(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    hint: &str,
+    width: f32,
+    error: Option<FormError>,
+) -> egui::Response {
+    let color = ui.visuals().error_fg_color;
+
+    let response = ui
+        .scope(|ui| {
+            if error.is_some() {
+                let widgets = &mut ui.visuals_mut().widgets;
+
+                for visuals in [
+                    &mut widgets.inactive,
+                    &mut widgets.hovered,
+                    &mut widgets.active,
+                ] {
+                    visuals.bg_stroke = Stroke::new(1.0, color);
+                }
+
+                ui.visuals_mut().selection.stroke = Stroke::new(1.0, color);
+            }
+
+            ui.add_sized(
+                [width, 18.0],
+                egui::TextEdit::singleline(text).hint_text(hint),
+            )
+        })
+        .inner;
+
+    if let Some(error) = error {
+        ui.colored_label(color, error.to_string());
+    }
+
+    response
+}
+
+// As h:mm, which `parse_duration` reads back
+fn format_duration(minutes: i64) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+pub fn format_time(time: Option<NaiveTime>) -> String {
+    time.map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_break_minutes() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+
+        let cases = [
+            ("", Some(0)),
+            ("30", Some(30)),
+            (" 45 ", Some(45)),
+            ("30m", Some(30)),
+            ("0:30", Some(30)),
+            ("1h", Some(60)),
+            ("0.5", Some(30)),
+            ("0", None),
+            ("600", None),
+        ];
+
+        for (break_time, expected) in cases {
+            let form = EntryForm {
+                duration: "8".to_string(),
+                break_time: break_time.to_string(),
+                ..EntryForm::new(date)
+            };
+
+            let minutes = form
+                .entry(TimeDefaults::default())
+                .ok()
+                .map(|entry| entry.break_minutes);
+
+            assert_eq!(minutes, expected, "{break_time:?}");
+        }
+    }
+}
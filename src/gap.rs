@@ -0,0 +1,122 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+
+use crate::entry::Entry;
+
+// Time in working hours between two entrys on the same day that neither
+// covers
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Gap {
+    pub date: NaiveDate,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Gap {
+    pub fn minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+}
+
+// The gaps between entrys as they read in `zone`, counting only what is
+// from `from` until `until` each day. Entrys of just a duration are left out
+// as when they were isn't known.
+pub fn find(entrys: &[Entry], zone: Tz, from: NaiveTime, until: NaiveTime, gaps: &mut Vec<Gap>) {
+    let mut spans: Vec<(NaiveDateTime, NaiveDateTime)> = entrys
+        .iter()
+        .filter_map(|entry| {
+            let entry = entry.in_zone(zone);
+            Some((
+                entry.date.and_time(entry.start?),
+                entry.end_date.and_time(entry.end?),
+            ))
+        })
+        .collect();
+
+    spans.sort();
+
+    // The latest end so far, entrys can be within others
+    let mut covered: Option<NaiveDateTime> = None;
+
+    for (start, end) in spans {
+        if let Some(covered) = covered.filter(|covered| *covered < start) {
+            let date = start.date();
+
+            if covered.date() == date {
+                let gap = Gap {
+                    date,
+                    start: covered.time().max(from),
+                    end: start.time().min(until),
+                };
+
+                if gap.start < gap.end {
+                    gaps.push(gap);
+                }
+            }
+        }
+
+        covered = Some(covered.map_or(end, |covered| covered.max(end)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn finds_gaps() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+
+        // Start and end hours and minutes
+        type Spans = &'static [(u32, u32, u32, u32)];
+
+        let cases: [(Spans, Spans); 7] = [
+            (&[(9, 0, 10, 0), (11, 0, 12, 0)], &[(10, 0, 11, 0)]),
+            (&[(11, 0, 12, 0), (9, 0, 10, 0)], &[(10, 0, 11, 0)]),
+            (
+                &[(9, 0, 11, 0), (10, 0, 12, 0), (13, 0, 14, 0)],
+                &[(12, 0, 13, 0)],
+            ),
+            (&[(9, 0, 17, 0), (10, 0, 11, 0), (12, 0, 13, 0)], &[]),
+            (&[(9, 0, 10, 0), (10, 0, 11, 0)], &[]),
+            // Only working hours count
+            (&[(8, 0, 9, 30), (16, 30, 18, 0)], &[(9, 30, 16, 30)]),
+            (&[(17, 30, 18, 0), (19, 0, 20, 0)], &[]),
+        ];
+
+        for (spans, expected) in cases {
+            let entrys: Vec<_> = spans
+                .iter()
+                .map(|&(h1, m1, h2, m2)| {
+                    Entry::new(
+                        date,
+                        String::new(),
+                        Some(time(h1, m1)),
+                        Some(time(h2, m2)),
+                        date,
+                        Tz::UTC,
+                        0,
+                    )
+                })
+                .collect();
+
+            let mut gaps = Vec::new();
+            find(&entrys, Tz::UTC, time(9, 0), time(17, 0), &mut gaps);
+
+            let expected: Vec<_> = expected
+                .iter()
+                .map(|&(h1, m1, h2, m2)| Gap {
+                    date,
+                    start: time(h1, m1),
+                    end: time(h2, m2),
+                })
+                .collect();
+
+            assert_eq!(gaps, expected, "{spans:?}");
+        }
+    }
+}
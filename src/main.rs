@@ -1,219 +1,1852 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use egui_extras::{Column, TableBuilder};
+use egui_extras::{Column, TableBuilder, TableRow};
 
-use chrono::{Local, NaiveDate};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate, NaiveTime, Utc};
 use eframe::egui::{self, FontData, FontDefinitions, IconData, RichText, Sense};
 use eframe::epaint::{FontFamily, Vec2};
 
-use datepicker::DatePicker;
-use entry::Entry;
-use utils::{
-    parse_difference, read_archive, read_entrys, wipe_entrys, write_entry, write_to_archive,
-};
+use backup::{Snapshot, Summary};
+use batch::Batch;
+use datepicker::DatePicker;
+use entry::Entry;
+use focus::{Focus, Phase};
+use form::{format_time, EntryForm};
+use gap::Gap;
+use overlap::Overlap;
+use settings::Settings;
+use storage::{MemoryStorage, Storage};
+use template::{Repeat, Template};
+use timer::Timer;
+use utils::{format_elapsed, format_hours, local_zone};
+use uuid::Uuid;
+
+mod backup;
+mod batch;
+mod csv;
+mod datepicker;
+mod entry;
+mod focus;
+mod form;
+mod gap;
+mod overlap;
+mod rounding;
+mod settings;
+mod sound;
+mod storage;
+mod template;
+mod timer;
+mod utils;
+
+struct WorkTracker {
+    storage: Box<dyn Storage>,
+    settings: Settings,
+    settings_open: bool,
+    backups_open: bool,
+    overlaps_open: bool,
+    templates_open: bool,
+    snapshots: Vec<(Snapshot, Option<Summary>)>,
+    // Where the data is being kept, None when it couldn't be resolved
+    data_dir: Option<PathBuf>,
+    data_dir_input: String,
+    // Held until exit so a second copy of Hours opens read only
+    _lock: Option<File>,
+    read_only: bool,
+    // Shown at the top until dismissed
+    errors: Vec<String>,
+    entrys: Vec<Entry>,
+    editing: Option<Editing>,
+    archive: Vec<Batch>,
+    // Ids of the archived batches showing their entrys
+    expanded: HashSet<u64>,
+    archive_label: String,
+    viewing_archive: bool,
+    total_minutes: i64,
+    total_minutes_archive: i64,
+    form: EntryForm,
+    confirming: Option<Confirming>,
+    // Saved as soon as it starts, until it is stopped into an entry
+    timer: Option<Timer>,
+    focus: Option<Focus>,
+    // Between the current entrys, less those that were ignored
+    overlaps: Vec<Overlap>,
+    // Pairs to stop warning about until the next start
+    ignored: HashSet<(Uuid, Uuid)>,
+    // Every overlap in the current entrys and the archive between two dates,
+    // found when the window is opened
+    overlap_report: Vec<Overlap>,
+    // The entrys the report was made from
+    report_entrys: Vec<Entry>,
+    report_from: NaiveDate,
+    report_to: NaiveDate,
+    // Shown between the entrys of their day
+    gaps: Vec<Gap>,
+    templates: Vec<Template>,
+    // The templates file couldn't be read, so it isn't saved over
+    templates_unread: bool,
+    // Entrys made from the repeating templates, waiting to be added or skipped
+    drafts: Vec<Entry>,
+    drafts_from: NaiveDate,
+    drafts_to: NaiveDate,
+    // What making drafts has to say, like a template that can't make an entry
+    drafts_notes: Vec<String>,
+}
+
+// An entry being changed in the edit window
+struct Editing {
+    id: Uuid,
+    form: EntryForm,
+}
+
+// A long entry waiting to be confirmed before it is added or saved
+struct Confirming {
+    entry: Entry,
+    new: bool,
+}
+
+enum RowAction {
+    Edit(usize),
+    Delete(usize),
+    // Fill the form in with a gap
+    Fill(usize),
+}
+
+enum CurrentRow {
+    Timer,
+    Entry(usize),
+    Gap(usize),
+}
+
+impl Default for WorkTracker {
+    fn default() -> Self {
+        Self {
+            storage: Box::new(MemoryStorage::default()),
+            settings: Settings::default(),
+            settings_open: false,
+            backups_open: false,
+            overlaps_open: false,
+            templates_open: false,
+            snapshots: Vec::new(),
+            data_dir: None,
+            data_dir_input: String::new(),
+            _lock: None,
+            read_only: false,
+            errors: Vec::new(),
+            entrys: Vec::new(),
+            editing: None,
+            archive: Vec::new(),
+            expanded: HashSet::new(),
+            archive_label: String::new(),
+            viewing_archive: false,
+            total_minutes: 0,
+            total_minutes_archive: 0,
+            form: EntryForm::new(Local::now().date_naive()),
+            confirming: None,
+            timer: None,
+            focus: None,
+            overlaps: Vec::new(),
+            ignored: HashSet::new(),
+            overlap_report: Vec::new(),
+            report_entrys: Vec::new(),
+            report_from: Local::now().date_naive() - chrono::Days::new(90),
+            report_to: Local::now().date_naive(),
+            gaps: Vec::new(),
+            templates: Vec::new(),
+            templates_unread: false,
+            drafts: Vec::new(),
+            drafts_from: Local::now().date_naive(),
+            drafts_to: Local::now().date_naive() + chrono::Days::new(6),
+            drafts_notes: Vec::new(),
+        }
+    }
+}
+
+impl WorkTracker {
+    fn new(
+        cc: &eframe::CreationContext<'_>,
+        storage: Box<dyn Storage>,
+        settings: Settings,
+    ) -> Self {
+        let mut fonts = FontDefinitions::default();
+
+        fonts.font_data.insert(
+            "ubuntu-light".to_owned(),
+            FontData::from_static(include_bytes!("../assets/Ubuntu-Light.ttf")).into(),
+        );
+
+        fonts
+            .families
+            .get_mut(&FontFamily::Proportional)
+            .unwrap()
+            .insert(0, "ubuntu-light".to_owned());
+
+        cc.egui_ctx.set_fonts(fonts);
+
+        cc.egui_ctx.set_pixels_per_point(1.1);
+
+        Self::with_storage(storage, settings)
+    }
+
+    fn with_storage(storage: Box<dyn Storage>, settings: Settings) -> Self {
+        let mut entrys = Vec::new();
+        let mut total_minutes = 0;
+        let mut errors = Vec::new();
+
+        if let Err(e) = storage.read_entrys(&mut entrys, &mut total_minutes) {
+            errors.push(format!("Couldn't read the entrys: {e}"));
+        }
+
+        let mut app = WorkTracker {
+            storage,
+            settings,
+            entrys,
+            total_minutes,
+            errors,
+            ..WorkTracker::default()
+        };
+
+        app.read_timer();
+        app.entrys_changed();
+        app
+    }
+
+    // Rereads everything, for picking up changes made by another copy of Hours
+    fn reload(&mut self) {
+        self.entrys.clear();
+        self.total_minutes = 0;
+
+        if let Err(e) = self
+            .storage
+            .read_entrys(&mut self.entrys, &mut self.total_minutes)
+        {
+            self.errors.push(format!("Couldn't read the entrys: {e}"));
+        }
+
+        self.read_timer();
+        self.entrys_changed();
+
+        self.archive.clear();
+        self.total_minutes_archive = 0;
+
+        if self.viewing_archive {
+            self.read_archive();
+        }
+    }
+
+    fn read_archive(&mut self) {
+        if let Err(e) = self
+            .storage
+            .read_archive(&mut self.archive, &mut self.total_minutes_archive)
+        {
+            self.errors.push(format!("Couldn't read the archive: {e}"));
+        }
+    }
+
+    // A timer whose entry was saved but couldn't be forgotten is left alone
+    fn read_timer(&mut self) {
+        match self.storage.read_timer() {
+            Ok(timer) => {
+                self.timer =
+                    timer.filter(|timer| !self.entrys.iter().any(|entry| entry.id == timer.id));
+            }
+            Err(e) => self.errors.push(format!("Couldn't read the timer: {e}")),
+        }
+    }
+
+    fn entrys_changed(&mut self) {
+        self.check_overlaps();
+        self.check_gaps();
+    }
+
+    // Only on days with current entrys, though archived ones on those days
+    // count as worked too
+    fn check_gaps(&mut self) {
+        self.gaps.clear();
+
+        let zone = local_zone();
+        let days: HashSet<NaiveDate> = self
+            .entrys
+            .iter()
+            .map(|entry| entry.in_zone(zone).date)
+            .collect();
+
+        let (Some(&from), Some(&to)) = (days.iter().min(), days.iter().max()) else {
+            return;
+        };
+
+        if !self.settings.show_gaps {
+            return;
+        }
+
+        let mut entrys = Vec::new();
+
+        // A day either side for entrys written in other zones
+        if let Err(e) = self.storage.entrys_between(
+            from.pred_opt().unwrap_or(from),
+            to.succ_opt().unwrap_or(to),
+            &mut entrys,
+        ) {
+            self.errors.push(format!("Couldn't read the entrys: {e}"));
+            entrys = self.entrys.clone();
+        }
+
+        gap::find(
+            &entrys,
+            zone,
+            hour(self.settings.work_from),
+            hour(self.settings.work_until),
+            &mut self.gaps,
+        );
+
+        self.gaps.retain(|gap| days.contains(&gap.date));
+    }
+
+    fn check_overlaps(&mut self) {
+        self.overlaps.clear();
+        overlap::find(&self.entrys, &mut self.overlaps);
+
+        self.overlaps
+            .retain(|overlap| !self.ignored.contains(&(overlap.first, overlap.second)));
+    }
+
+    // Current and archived entrys in the dates chosen in the window
+    fn report_overlaps(&mut self) {
+        self.overlap_report.clear();
+        self.report_entrys.clear();
+
+        if let Err(e) =
+            self.storage
+                .entrys_between(self.report_from, self.report_to, &mut self.report_entrys)
+        {
+            self.errors.push(format!("Couldn't read the entrys: {e}"));
+        }
+
+        overlap::find(&self.report_entrys, &mut self.overlap_report);
+    }
+
+    // A current or archived entry and where it is
+    fn find_entry(&self, id: Uuid) -> Option<(&Entry, String)> {
+        if let Some(entry) = self.entrys.iter().find(|entry| entry.id == id) {
+            return Some((entry, "Current".to_string()));
+        }
+
+        let archived = self.archive.iter().find_map(|batch| {
+            let entry = batch.entrys.iter().find(|entry| entry.id == id)?;
+            Some((entry, batch.title()))
+        });
+
+        // The batch is only known once the archive has been opened
+        archived.or_else(|| {
+            let entry = self.report_entrys.iter().find(|entry| entry.id == id)?;
+            Some((entry, "Archived".to_string()))
+        })
+    }
+
+    // A warning for each overlap in the current entrys with what can be done
+    // about it
+    fn overlap_warnings(&mut self, ui: &mut egui::Ui) {
+        let mut action = None;
+        let mut ignore = None;
+
+        for &overlap in &self.overlaps {
+            let (Some((first, _)), Some((second, _))) = (
+                self.find_entry(overlap.first),
+                self.find_entry(overlap.second),
+            ) else {
+                continue;
+            };
+
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "\"{}\" and \"{}\" on {} overlap by {} hours",
+                        first.description,
+                        second.description,
+                        first.date,
+                        format_hours(overlap.minutes)
+                    ),
+                );
+
+                ui.add_enabled_ui(!self.read_only, |ui| {
+                    let trimmed = overlap::trim(first, second);
+
+                    if ui
+                        .add_enabled(trimmed.is_some(), egui::Button::new("Trim"))
+                        .on_hover_text("Start the later entry when the earlier one ends")
+                        .on_disabled_hover_text("The later entry is within the earlier one")
+                        .clicked()
+                    {
+                        action = trimmed.map(|entry| (overlap, entry, false));
+                    }
+
+                    if ui
+                        .button("Merge")
+                        .on_hover_text("Make them one entry")
+                        .clicked()
+                    {
+                        action = overlap::merge(first, second).map(|entry| (overlap, entry, true));
+                    }
+                });
+
+                if ui.button("Ignore").clicked() {
+                    ignore = Some(overlap);
+                }
+            });
+        }
+
+        if let Some(overlap) = ignore {
+            self.ignored.insert((overlap.first, overlap.second));
+            self.check_overlaps();
+        }
+
+        if let Some((overlap, entry, merged)) = action {
+            let entry = self.billed(entry);
+
+            if self.update_entry(entry.id, Some(entry)) && merged {
+                self.update_entry(overlap.second, None);
+            }
+        }
+    }
+
+    fn overlaps_window(&mut self, ui: &mut egui::Ui) {
+        let mut check = false;
+
+        ui.horizontal(|ui| {
+            ui.label("From");
+            ui.add(DatePicker::new("report_from", &mut self.report_from));
+            ui.label("to");
+            ui.add(DatePicker::new("report_to", &mut self.report_to));
+
+            check = ui.button("Check").clicked();
+        });
+
+        if check {
+            self.report_overlaps();
+        }
+
+        if self.overlap_report.is_empty() {
+            ui.label("No entrys overlap.");
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                egui::Grid::new("overlaps").striped(true).show(ui, |ui| {
+                    ui.strong("Date");
+                    ui.strong("Entry");
+                    ui.strong("Overlaps");
+                    ui.strong("Hours");
+                    ui.end_row();
+
+                    for overlap in &self.overlap_report {
+                        let (Some((first, first_in)), Some((second, second_in))) = (
+                            self.find_entry(overlap.first),
+                            self.find_entry(overlap.second),
+                        ) else {
+                            continue;
+                        };
+
+                        let describe = |entry: &Entry, found_in: String| {
+                            format!(
+                                "{} - {} {} ({found_in})",
+                                format_time(entry.start),
+                                format_time(entry.end),
+                                entry.description
+                            )
+                        };
+
+                        ui.label(first.date.to_string());
+                        ui.label(describe(first, first_in));
+                        ui.label(describe(second, second_in));
+                        ui.label(format_hours(overlap.minutes));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    fn top_input(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("input").show(ctx, |ui| {
+            ui.add_space(10.0);
+
+            if self.read_only {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Another copy of Hours is open so this one is read only.",
+                    );
+
+                    if ui.button("Reload").clicked() {
+                        self.reload();
+                    }
+                });
+                ui.add_space(6.0);
+            }
+
+            let mut dismissed = None;
+
+            for (i, error) in self.errors.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        dismissed = Some(i);
+                    }
+
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                });
+            }
+
+            if let Some(i) = dismissed {
+                self.errors.remove(i);
+            }
+
+            if !self.errors.is_empty() {
+                ui.add_space(6.0);
+            }
+
+            if !self.overlaps.is_empty() && !self.viewing_archive {
+                self.overlap_warnings(ui);
+                ui.add_space(6.0);
+            }
+
+            ui.add_enabled_ui(!self.viewing_archive && !self.read_only, |ui| {
+                self.input(ui);
+            });
+
+            ui.add_space(10.0);
+        });
+    }
+
+    fn input(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            self.form.times(ui, "date", self.settings.times);
+
+            ui.add_sized(
+                [200.0, 18.0],
+                egui::TextEdit::singleline(&mut self.form.description).hint_text("Description"),
+            );
+
+            if ui.button("Add entry").clicked() {
+                match self.form.entry(self.settings.times) {
+                    Ok(entry) => self.submit(entry, true),
+                    Err(_) => self.form.tried = true,
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            self.template_input(ui);
+            self.timer_input(ui);
+        });
+    }
+
+    fn template_input(&mut self, ui: &mut egui::Ui) {
+        let mut picked = None;
+        let mut save = false;
+
+        egui::ComboBox::from_id_salt("template")
+            .selected_text("Template")
+            .width(90.0)
+            .show_ui(ui, |ui| {
+                for (i, template) in self.templates.iter().enumerate() {
+                    if ui.selectable_label(false, template.label()).clicked() {
+                        picked = Some(i);
+                    }
+                }
+
+                if !self.templates.is_empty() {
+                    ui.separator();
+                }
+
+                save = ui
+                    .button("Save as template")
+                    .on_hover_text("The description and times typed above")
+                    .clicked();
+            });
+
+        // Keeps the date that was picked
+        if let Some(i) = picked {
+            self.form = self.templates[i].form(self.form.date);
+        }
+
+        if save {
+            self.templates.push(Template::from_form(&self.form));
+            self.save_templates();
+        }
+    }
+
+    // Nothing is kept when ephemeral or read only, like the entrys
+    fn save_templates(&mut self) {
+        let Some(dir) = self.data_dir.as_ref().filter(|_| !self.read_only) else {
+            return;
+        };
+
+        if self.templates_unread {
+            self.errors.push(
+                "The templates aren't saved as the templates file couldn't be read".to_string(),
+            );
+            return;
+        }
+
+        if let Err(e) = template::save(dir, &self.templates) {
+            self.errors
+                .push(format!("Couldn't save the templates: {e}"));
+        }
+    }
+
+    fn templates_window(&mut self, ui: &mut egui::Ui) {
+        if self.templates.is_empty() {
+            ui.label("Save what is typed in the input row as a template from its dropdown.");
+        }
+
+        let mut delete = None;
+        let mut changed = false;
+
+        egui::Grid::new("templates").striped(true).show(ui, |ui| {
+            for (i, template) in self.templates.iter_mut().enumerate() {
+                ui.label(template.label());
+
+                let recurrence = &mut template.recurrence;
+
+                egui::ComboBox::from_id_salt(("repeat", i))
+                    .selected_text(recurrence.repeat.name())
+                    .show_ui(ui, |ui| {
+                        for repeat in Repeat::ALL {
+                            changed |= ui
+                                .selectable_value(&mut recurrence.repeat, repeat, repeat.name())
+                                .changed();
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    if recurrence.repeat != Repeat::Weekly {
+                        return;
+                    }
+
+                    for (day, name) in ["M", "T", "W", "T", "F", "S", "S"].iter().enumerate() {
+                        changed |= ui.checkbox(&mut recurrence.days[day], *name).changed();
+                    }
+
+                    ui.label("every");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut recurrence.every).range(1..=52))
+                        .changed();
+                    ui.label("weeks");
+                });
+
+                ui.horizontal(|ui| {
+                    if recurrence.repeat == Repeat::Never {
+                        return;
+                    }
+
+                    ui.label("from");
+                    changed |= ui
+                        .add(DatePicker::new(("since", i), &mut recurrence.since))
+                        .changed();
+                });
+
+                if ui.button("Delete").clicked() {
+                    delete = Some(i);
+                }
+
+                ui.end_row();
+            }
+        });
+
+        if let Some(i) = delete {
+            self.templates.remove(i);
+            changed = true;
+        }
+
+        if changed {
+            self.save_templates();
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Drafts from");
+            ui.add(DatePicker::new("drafts_from", &mut self.drafts_from));
+            ui.label("to");
+            ui.add(DatePicker::new("drafts_to", &mut self.drafts_to));
+
+            if ui.button("Make drafts").clicked() {
+                self.make_drafts();
+            }
+        });
+
+        for note in &self.drafts_notes {
+            ui.label(note);
+        }
+
+        if self.drafts.is_empty() {
+            return;
+        }
+
+        let mut add = None;
+        let mut skip = None;
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("drafts").striped(true).show(ui, |ui| {
+                    for (i, draft) in self.drafts.iter().enumerate() {
+                        ui.label(draft.date.format("%a %Y-%m-%d").to_string());
+                        ui.label(format!(
+                            "{} - {}",
+                            format_time(draft.start),
+                            format_time(draft.end)
+                        ));
+                        ui.label(&draft.description);
+
+                        if ui
+                            .add_enabled(!self.read_only, egui::Button::new("Add"))
+                            .clicked()
+                        {
+                            add = Some(i);
+                        }
+
+                        if ui.button("Skip").clicked() {
+                            skip = Some(i);
+                        }
+
+                        ui.end_row();
+                    }
+                });
+            });
+
+        let mut all = false;
+
+        ui.horizontal(|ui| {
+            all = ui
+                .add_enabled(!self.read_only, egui::Button::new("Add all"))
+                .clicked();
+
+            if ui.button("Skip all").clicked() {
+                self.drafts.clear();
+            }
+        });
+
+        if let Some(i) = add {
+            if self.add_entry(self.billed(self.drafts[i].clone())) {
+                self.drafts.remove(i);
+            }
+        } else if let Some(i) = skip {
+            self.drafts.remove(i);
+        } else if all {
+            // Any that couldn't be added are left to try again
+            let drafts = std::mem::take(&mut self.drafts);
+
+            for draft in drafts {
+                if !self.add_entry(self.billed(draft.clone())) {
+                    self.drafts.push(draft);
+                }
+            }
+        }
+    }
+
+    // An entry for each day in the range that a template repeats on, unless
+    // one like it was already logged, like from making drafts before
+    fn make_drafts(&mut self) {
+        self.drafts.clear();
+        self.drafts_notes.clear();
+
+        // Each template is only mentioned once, not for every day
+        let mut failed = HashSet::new();
+
+        let days = self
+            .drafts_from
+            .iter_days()
+            .take_while(|date| *date <= self.drafts_to)
+            .take(366);
+
+        for date in days {
+            for (i, template) in self.templates.iter().enumerate() {
+                if !template.recurrence.on(date) {
+                    continue;
+                }
+
+                let entry = match template.form(date).entry(self.settings.times) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        if failed.insert(i) {
+                            self.drafts_notes
+                                .push(format!("\"{}\" can't make an entry: {e}", template.label()));
+                        }
+                        continue;
+                    }
+                };
+
+                let logged = self.entrys.iter().any(|logged| {
+                    logged.date == entry.date
+                        && logged.start == entry.start
+                        && logged.description == entry.description
+                });
+
+                if !logged {
+                    self.drafts.push(entry);
+                }
+            }
+        }
+
+        if self.drafts.is_empty() && self.drafts_notes.is_empty() {
+            self.drafts_notes
+                .push("No templates repeat on those days that aren't already logged.".to_string());
+        }
+    }
+
+    fn timer_input(&mut self, ui: &mut egui::Ui) {
+        if self.focus.is_some() {
+            self.focus_input(ui);
+            return;
+        }
+
+        let Some(timer) = &self.timer else {
+            if ui
+                .button("Start timer")
+                .on_hover_text("Times from now, with the description typed above")
+                .clicked()
+            {
+                let description = self.form.description.trim().to_string();
+
+                if self.start_timer(description) {
+                    self.form.description.clear();
+                }
+            }
+
+            let (work, rest) = (
+                self.settings.focus_minutes,
+                self.settings.focus_break_minutes,
+            );
+
+            if ui
+                .button("Focus")
+                .on_hover_text(format!(
+                    "{work} minutes of work then a {rest} minute break, over and over. \
+                    Only the work is logged."
+                ))
+                .clicked()
+            {
+                let description = self.form.description.trim().to_string();
+
+                if self.start_timer(description.clone()) {
+                    self.form.description.clear();
+                    self.focus = Some(Focus::new(description, work.into(), rest.into()));
+                }
+            }
+
+            return;
+        };
+
+        ui.label(format!(
+            "Running for {}",
+            format_elapsed(timer.elapsed(Utc::now()))
+        ));
+
+        if ui.button("Stop").clicked() {
+            match timer.entry(Utc::now()) {
+                Some(entry) => self.submit(entry, true),
+                None => self
+                    .errors
+                    .push("Less than a minute has passed, cancel the timer instead".to_string()),
+            }
+        } else if ui.button("Cancel").clicked() {
+            match self.storage.write_timer(None) {
+                Ok(()) => self.timer = None,
+                Err(e) => self.errors.push(format!("Couldn't cancel the timer: {e}")),
+            }
+        }
+    }
+
+    // The timer only runs once it has been saved so a restart can't lose it
+    fn start_timer(&mut self, description: String) -> bool {
+        let timer = Timer::new(local_zone(), description);
+
+        if let Err(e) = self.storage.write_timer(Some(&timer)) {
+            self.errors.push(format!("Couldn't start the timer: {e}"));
+            return false;
+        }
+
+        self.timer = Some(timer);
+        true
+    }
+
+    fn focus_input(&mut self, ui: &mut egui::Ui) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+
+        let left = focus
+            .ends(self.timer.as_ref())
+            .map(|ends| (ends - Utc::now()).num_seconds().max(0))
+            .unwrap_or(0);
+
+        let text = match focus.phase {
+            Phase::Work => format!("Focus {}, {} left", focus.done + 1, format_elapsed(left)),
+            Phase::Break(_) => format!("Break, {} left", format_elapsed(left)),
+        };
+
+        if focus.flashing() {
+            ui.label(
+                RichText::new(text)
+                    .strong()
+                    .color(ui.visuals().warn_fg_color),
+            );
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+        } else {
+            ui.strong(text);
+        }
+
+        if !ui
+            .button("End session")
+            .on_hover_text("Logs the work so far")
+            .clicked()
+        {
+            return;
+        }
+
+        if let (Phase::Work, Some(timer)) = (focus.phase, &self.timer) {
+            match timer.entry(Utc::now()) {
+                Some(entry) => self.submit(entry, true),
+                None => match self.storage.write_timer(None) {
+                    Ok(()) => self.timer = None,
+                    Err(e) => self.errors.push(format!("Couldn't cancel the timer: {e}")),
+                },
+            }
+        }
+
+        self.focus = None;
+    }
+
+    // Logs the work once it is over and starts the next after the break
+    fn focus_tick(&mut self, ctx: &egui::Context) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+
+        // Stopped or cancelled from elsewhere, so the session is over
+        let Some(ends) = focus.ends(self.timer.as_ref()) else {
+            self.focus = None;
+            return;
+        };
+
+        let now = Utc::now();
+
+        if now < ends {
+            return;
+        }
+
+        let (phase, rest, description) =
+            (focus.phase, focus.break_minutes, focus.description.clone());
+
+        let next = match phase {
+            Phase::Work => {
+                if let Some(entry) = self.timer.as_ref().and_then(|timer| timer.entry(ends)) {
+                    let id = entry.id;
+                    self.submit(entry, true);
+
+                    // Not saved, or waiting to be confirmed. The timer is left
+                    // running to be stopped by hand.
+                    if self.timer.as_ref().is_some_and(|timer| timer.id == id) {
+                        self.focus = None;
+                        return;
+                    }
+                }
+
+                Phase::Break(now + chrono::Duration::minutes(rest))
+            }
+            Phase::Break(_) => {
+                if !self.start_timer(description) {
+                    self.focus = None;
+                    return;
+                }
+
+                Phase::Work
+            }
+        };
+
+        if let Some(focus) = &mut self.focus {
+            if let Phase::Work = phase {
+                focus.done += 1;
+            }
+
+            focus.phase = next;
+            focus.changed = Instant::now();
+        }
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+            egui::UserAttentionType::Informational,
+        ));
+
+        if self.settings.focus_sound {
+            sound::chime();
+        }
+    }
+
+    // With the billed minutes rounded as set in Settings
+    fn billed(&self, entry: Entry) -> Entry {
+        Entry {
+            billed_minutes: self.settings.rounding.entry(entry.minutes),
+            ..entry
+        }
+    }
+
+    // Adds or saves the entry, unless it is long enough to need confirming first
+    fn submit(&mut self, entry: Entry, new: bool) {
+        let entry = self.billed(entry);
+
+        let limit = i64::from(self.settings.confirm_over_hours) * 60;
+
+        if limit > 0 && entry.gross_minutes() > limit {
+            self.confirming = Some(Confirming { entry, new });
+        } else {
+            self.apply(entry, new);
+        }
+    }
+
+    // What was typed is kept if it couldn't be saved so it isn't lost
+    fn apply(&mut self, entry: Entry, new: bool) {
+        if new {
+            // A stopped timer's entry didn't come from the form
+            let timed = self
+                .timer
+                .as_ref()
+                .is_some_and(|timer| timer.id == entry.id);
+
+            if self.add_entry(entry) && !timed {
+                self.form.clear();
+            }
+        } else if self.update_entry(entry.id, Some(entry)) {
+            self.editing = None;
+        }
+    }
+
+    // Only shows the entry once it has been saved
+    fn add_entry(&mut self, entry: Entry) -> bool {
+        if let Err(e) = self.storage.write_entry(&entry) {
+            self.errors.push(format!("Couldn't save the entry: {e}"));
+            return false;
+        }
+
+        // The timer it was stopped from is done with once it is saved
+        if self
+            .timer
+            .as_ref()
+            .is_some_and(|timer| timer.id == entry.id)
+        {
+            self.timer = None;
+
+            if let Err(e) = self.storage.write_timer(None) {
+                self.errors.push(format!("Couldn't clear the timer: {e}"));
+            }
+        }
+
+        self.total_minutes += entry.minutes;
+        self.entrys.push(entry);
+        self.entrys.sort_by(Entry::by_date);
+        self.entrys_changed();
+
+        true
+    }
+
+    fn confirm_window(&mut self, ui: &mut egui::Ui) {
+        let Some(confirming) = &self.confirming else {
+            return;
+        };
+
+        let entry = &confirming.entry;
+
+        ui.label(format!(
+            "This entry is {} hours long, from {} {} to {} {}.",
+            format_hours(entry.gross_minutes()),
+            entry.date,
+            format_time(entry.start),
+            entry.end_date,
+            format_time(entry.end),
+        ));
+
+        ui.add_space(6.0);
+
+        let mut keep = false;
+        let mut back = false;
+
+        ui.horizontal(|ui| {
+            keep = ui.button("Keep it").clicked();
+            back = ui.button("Go back").clicked();
+        });
+
+        if keep {
+            if let Some(confirming) = self.confirming.take() {
+                self.apply(confirming.entry, confirming.new);
+            }
+        } else if back {
+            self.confirming = None;
+        }
+    }
+
+    fn settings_window(&mut self, ui: &mut egui::Ui) {
+        let in_use = match &self.data_dir {
+            Some(dir) => dir.display().to_string(),
+            None => "None, nothing is being saved".to_string(),
+        };
+
+        ui.label(format!("Data directory in use: {in_use}"));
+
+        ui.horizontal(|ui| {
+            ui.label("Data directory");
+
+            let default = settings::default_data_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+
+            ui.add_sized(
+                [300.0, 18.0],
+                egui::TextEdit::singleline(&mut self.data_dir_input).hint_text(default),
+            );
+        });
+
+        ui.label("--data-dir and HOURS_DATA_DIR override this.");
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Keep the last");
+            ui.add(egui::DragValue::new(&mut self.settings.backups.keep).range(1..=1000));
+            ui.label("backups");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Remove backups older than");
+            ui.add(egui::DragValue::new(
+                &mut self.settings.backups.max_age_days,
+            ));
+            ui.label("days (0 keeps them)");
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Times without am/pm before");
+            ui.add(egui::DragValue::new(&mut self.settings.times.pm_before).range(0..=12));
+            ui.label("are in the afternoon (0 turns this off)");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Ask before keeping entrys over");
+            ui.add(egui::DragValue::new(&mut self.settings.confirm_over_hours).range(0..=168));
+            ui.label("hours (0 never asks)");
+        });
+
+        ui.separator();
+
+        let rounding = &mut self.settings.rounding;
+
+        ui.horizontal(|ui| {
+            ui.label("Round billed time");
+
+            egui::ComboBox::from_id_salt("rounding_mode")
+                .selected_text(rounding.mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in rounding::Mode::ALL {
+                        ui.selectable_value(&mut rounding.mode, mode, mode.name());
+                    }
+                });
+
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut rounding.minutes).range(1..=60));
+            ui.label("minutes for each");
+
+            egui::ComboBox::from_id_salt("rounding_scope")
+                .selected_text(rounding.scope.name())
+                .show_ui(ui, |ui| {
+                    for scope in rounding::Scope::ALL {
+                        ui.selectable_value(&mut rounding.scope, scope, scope.name());
+                    }
+                });
+        });
+
+        ui.label("Entrys keep the billed time they were saved with.");
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Focus for");
+            ui.add(egui::DragValue::new(&mut self.settings.focus_minutes).range(1..=180));
+            ui.label("minutes with a break of");
+            ui.add(egui::DragValue::new(&mut self.settings.focus_break_minutes).range(1..=60));
+            ui.label("minutes");
+        });
+
+        ui.add_enabled(
+            sound::AVAILABLE,
+            egui::Checkbox::new(
+                &mut self.settings.focus_sound,
+                "Play a sound when a work or break is over",
+            ),
+        )
+        .on_disabled_hover_text("There is no system sound to play on this platform");
+
+        ui.separator();
+
+        let mut gaps = false;
+
+        ui.horizontal(|ui| {
+            gaps |= ui
+                .checkbox(
+                    &mut self.settings.show_gaps,
+                    "Show gaps between entrys from",
+                )
+                .changed();
+            gaps |= ui
+                .add(egui::DragValue::new(&mut self.settings.work_from).range(0..=23))
+                .changed();
+            ui.label("to");
+            gaps |= ui
+                .add(egui::DragValue::new(&mut self.settings.work_until).range(0..=23))
+                .changed();
+        });
+
+        if gaps {
+            self.check_gaps();
+        }
+
+        ui.separator();
+
+        ui.checkbox(
+            &mut self.settings.local_times,
+            format!(
+                "Show times in the current zone ({}) instead of the zone of each entry",
+                local_zone()
+            ),
+        );
+
+        ui.separator();
+
+        ui.label("Changes apply on restart.");
+
+        if ui.button("Save").clicked() {
+            let input = self.data_dir_input.trim();
+            self.settings.data_dir = (!input.is_empty()).then(|| PathBuf::from(input));
+
+            if let Err(e) = self.settings.save() {
+                self.errors.push(format!("Couldn't save settings: {e}"));
+            }
+        }
+    }
+
+    fn load_snapshots(&mut self) {
+        self.snapshots.clear();
+
+        let dir = if let Some(dir) = &self.data_dir {
+            dir
+        } else {
+            return;
+        };
+
+        match backup::list(dir) {
+            Ok(snapshots) => {
+                self.snapshots = snapshots
+                    .into_iter()
+                    .map(|snapshot| {
+                        let summary = backup::summarize(&snapshot).ok();
+                        (snapshot, summary)
+                    })
+                    .collect();
+            }
+            Err(e) => self.errors.push(format!("Couldn't list backups: {e}")),
+        }
+    }
+
+    fn restore(&mut self, snapshot: PathBuf) {
+        let dir = if let Some(dir) = &self.data_dir {
+            dir.clone()
+        } else {
+            return;
+        };
+
+        let retention = self.settings.backups;
+
+        // Close the database before its file is replaced
+        self.storage = Box::new(MemoryStorage::default());
+
+        let result = backup::restore(&dir, &snapshot, retention);
+
+        self.storage = storage::open(dir, false, false, retention, &mut self.errors);
+
+        if let Err(e) = result {
+            self.errors.push(format!("Couldn't restore backup: {e}"));
+        }
+
+        self.reload();
+        self.load_snapshots();
+    }
+
+    fn backups_window(&mut self, ui: &mut egui::Ui) {
+        if self.snapshots.is_empty() {
+            ui.label("There are no backups yet.");
+        }
+
+        let mut restore = None;
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("backups").striped(true).show(ui, |ui| {
+                    for (snapshot, summary) in &self.snapshots {
+                        ui.label(&snapshot.name);
+
+                        match summary {
+                            Some(summary) => ui.label(format!(
+                                "{} entrys, {} hours | archive {} entrys, {} hours",
+                                summary.entrys,
+                                format_hours(summary.minutes),
+                                summary.archived,
+                                format_hours(summary.archived_minutes)
+                            )),
+                            None => ui.label("Couldn't be read"),
+                        };
+
+                        if ui
+                            .add_enabled(!self.read_only, egui::Button::new("Restore"))
+                            .clicked()
+                        {
+                            restore = Some(snapshot.path.clone());
+                        }
+
+                        ui.end_row();
+                    }
+                });
+            });
+
+        ui.label("The current data is backed up before restoring.");
+
+        if let Some(snapshot) = restore {
+            self.restore(snapshot);
+        }
+    }
+
+    // Returns what was picked from a row's right click menu
+    fn body(&self, body: egui_extras::TableBody) -> Option<RowAction> {
+        if self.entrys.is_empty() && self.timer.is_none() {
+            return None;
+        }
+
+        // The running timer goes above the entrys and the gaps of a day after
+        // its last entry
+        let mut rows = Vec::new();
+
+        if self.timer.is_some() {
+            rows.push(CurrentRow::Timer);
+        }
+
+        for (i, entry) in self.entrys.iter().enumerate() {
+            rows.push(CurrentRow::Entry(i));
+
+            if self
+                .entrys
+                .get(i + 1)
+                .is_none_or(|next| next.date != entry.date)
+            {
+                rows.extend(
+                    (0..self.gaps.len())
+                        .filter(|&j| self.gaps[j].date == entry.date)
+                        .map(CurrentRow::Gap),
+                );
+            }
+        }
+
+        let mut action = None;
 
-mod datepicker;
-mod entry;
-mod utils;
+        body.rows(18.0, rows.len() + 1, |mut row| {
+            let row_index = match rows.get(row.index()) {
+                Some(CurrentRow::Timer) => {
+                    if let Some(timer) = &self.timer {
+                        timer_row(&mut row, timer, &self.settings);
+                    }
+                    return;
+                }
+                Some(&CurrentRow::Gap(j)) => {
+                    gap_row(&mut row, &self.gaps[j]);
 
-struct WorkTracker {
-    entrys: Vec<Entry>,
-    archive: Vec<Entry>,
-    viewing_archive: bool,
-    total_hours: f64,
-    total_hours_archive: f64,
-    date: NaiveDate,
-    description: String,
-    start_time: String,
-    end_time: String,
-}
+                    if !self.read_only && row.response().clicked() {
+                        action = Some(RowAction::Fill(j));
+                    }
+                    return;
+                }
+                Some(&CurrentRow::Entry(i)) => i,
+                None => {
+                    self.total_row(&mut row);
+                    return;
+                }
+            };
 
-impl Default for WorkTracker {
-    fn default() -> Self {
-        Self {
-            entrys: Vec::new(),
-            archive: Vec::new(),
-            viewing_archive: false,
-            total_hours: 0.0,
-            total_hours_archive: 0.0,
-            date: Local::now().date_naive(),
-            description: String::new(),
-            start_time: String::new(),
-            end_time: String::new(),
-        }
+            let entry = &self.entrys[row_index];
+
+            // Highlight entrys that overlap another
+            if self
+                .overlaps
+                .iter()
+                .any(|overlap| overlap.first == entry.id || overlap.second == entry.id)
+            {
+                row.set_selected(true);
+            }
+
+            entry_row(&mut row, entry, &self.settings);
+
+            if self.read_only {
+                return;
+            }
+
+            let response = row.response();
+
+            if response.double_clicked() {
+                action = Some(RowAction::Edit(row_index));
+            }
+
+            response.context_menu(|ui| {
+                if ui.button("Edit").clicked() {
+                    action = Some(RowAction::Edit(row_index));
+                    ui.close_menu();
+                }
+
+                if ui.button("Delete").clicked() {
+                    action = Some(RowAction::Delete(row_index));
+                    ui.close_menu();
+                }
+            });
+        });
+
+        action
     }
-}
 
-impl WorkTracker {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut fonts = FontDefinitions::default();
+    // Show total hours in the last row
+    fn total_row(&self, row: &mut TableRow) {
+        row.col(|_| {});
+        row.col(|ui| {
+            ui.label(format_hours(self.total_minutes));
+        });
+        row.col(|ui| {
+            ui.label(format_hours(gross_minutes(&self.entrys)));
+        });
+        row.col(|ui| {
+            let billed = self.settings.rounding.total(&self.entrys);
+            billed_label(ui, billed, self.total_minutes);
+        });
+    }
 
-        fonts.font_data.insert(
-            "ubuntu-light".to_owned(),
-            FontData::from_static(include_bytes!("../assets/Ubuntu-Light.ttf")).into(),
+    fn edit_window(&mut self, ui: &mut egui::Ui) {
+        let times = self.settings.times;
+
+        let Some(editing) = &mut self.editing else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            editing.form.times(ui, "edit_date", times);
+        });
+
+        ui.add_sized(
+            [300.0, 18.0],
+            egui::TextEdit::singleline(&mut editing.form.description).hint_text("Description"),
         );
 
-        fonts
-            .families
-            .get_mut(&FontFamily::Proportional)
-            .unwrap()
-            .insert(0, "ubuntu-light".to_owned());
+        ui.add_space(6.0);
 
-        cc.egui_ctx.set_fonts(fonts);
+        let id = editing.id;
+        let mut save = None;
+        let mut delete = false;
+        let mut close = false;
 
-        cc.egui_ctx.set_pixels_per_point(1.1);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                match editing.form.entry(times) {
+                    Ok(entry) => save = Some(Entry { id, ..entry }),
+                    Err(_) => editing.form.tried = true,
+                }
+            }
 
-        let mut entrys = Vec::new();
-        let mut total_hours = 0.0;
+            delete = ui.button("Delete").clicked();
+            close = ui.button("Cancel").clicked();
+        });
 
-        read_entrys(&mut entrys, &mut total_hours).ok();
-        let total_hours = total_hours.clone();
+        if let Some(entry) = save {
+            self.submit(entry, false);
+        } else if delete {
+            close = self.update_entry(id, None);
+        }
 
-        WorkTracker {
-            entrys,
-            total_hours,
-            ..WorkTracker::default()
+        if close {
+            self.editing = None;
         }
     }
 
-    fn top_input(&mut self, ctx: &egui::Context) {
-        egui::TopBottomPanel::top("input").show(ctx, |ui| {
-            ui.add_space(10.0);
+    // Replaces the entry with `new`, or removes it if None. The entrys are
+    // only changed once the storage has been, false if it couldn't be.
+    fn update_entry(&mut self, id: Uuid, new: Option<Entry>) -> bool {
+        let result = match &new {
+            Some(new) => self.storage.update_entry(new),
+            None => self.storage.delete_entry(id),
+        };
 
-            ui.add_enabled_ui(!self.viewing_archive, |ui| {
-                self.input(ui);
-            });
+        if let Err(e) = result {
+            self.errors.push(format!("Couldn't change the entry: {e}"));
+            return false;
+        }
 
-            ui.add_space(10.0);
-        });
+        if let Some(index) = self.entrys.iter().position(|entry| entry.id == id) {
+            match new {
+                Some(new) => self.entrys[index] = new,
+                None => {
+                    self.entrys.remove(index);
+                }
+            }
+        }
+
+        self.entrys.sort_by(Entry::by_date);
+        self.total_minutes = self.entrys.iter().map(|entry| entry.minutes).sum();
+        self.entrys_changed();
+
+        true
     }
 
-    fn input(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.add(DatePicker::new("date", &mut self.date));
+    // The entrys are only cleared once they are safely archived
+    fn archive_all(&mut self) {
+        self.entrys.sort_by(Entry::by_date);
 
-            ui.add_sized(
-                [60.0, 18.0],
-                egui::TextEdit::singleline(&mut self.start_time).hint_text("1:30pm"),
-            );
+        // Rounded as set now and kept that way
+        let billed = self.settings.rounding.total(&self.entrys);
 
-            ui.label(RichText::new("-").size(18.0));
+        match self
+            .storage
+            .archive_entrys(&self.entrys, self.archive_label.trim(), billed)
+        {
+            Ok(()) => {
+                self.entrys.clear();
+                self.total_minutes = 0;
+                self.archive_label.clear();
+                self.entrys_changed();
 
-            ui.add_sized(
-                [60.0, 18.0],
-                egui::TextEdit::singleline(&mut self.end_time).hint_text("10:30pm"),
-            );
+                // Reread the archive next time it is opened
+                self.archive.clear();
+                self.total_minutes_archive = 0;
+            }
+            Err(e) => self
+                .errors
+                .push(format!("Couldn't archive the entrys: {e}")),
+        }
+    }
 
-            ui.add_sized(
-                [200.0, 18.0],
-                egui::TextEdit::singleline(&mut self.description).hint_text("Description"),
-            );
+    // Newest batch first, each with a row that opens to show its entrys.
+    // Returns the id of a batch that was opened or closed.
+    fn archive_body(&self, body: egui_extras::TableBody) -> Option<u64> {
+        if self.archive.is_empty() {
+            return None;
+        }
 
-            if ui.button("Add entry").clicked() {
-                let start_time = self.start_time.to_ascii_lowercase();
-                let end_time = self.end_time.to_ascii_lowercase();
+        let mut rows = Vec::new();
+
+        for (i, batch) in self.archive.iter().enumerate().rev() {
+            rows.push(ArchiveRow::Batch(i));
 
-                let hours = parse_difference(&start_time, &end_time);
+            if self.expanded.contains(&batch.id) {
+                rows.extend((0..batch.entrys.len()).map(|j| ArchiveRow::Entry(i, j)));
+            }
+        }
 
-                if let Some(hours) = hours {
-                    self.total_hours += hours;
+        let mut toggled = None;
 
-                    let date = self.date.format("%Y-%m-%d").to_string();
+        body.rows(18.0, rows.len() + 1, |mut row| {
+            match rows.get(row.index()) {
+                Some(&ArchiveRow::Batch(i)) => {
+                    let batch = &self.archive[i];
 
-                    let entry =
-                        Entry::new(date, self.description.clone(), start_time, end_time, hours);
+                    let arrow = if self.expanded.contains(&batch.id) {
+                        "▼"
+                    } else {
+                        "▶"
+                    };
 
-                    write_entry(&entry);
+                    let archived_at = batch
+                        .archived_at
+                        .map(|at| at.format("%Y-%m-%d").to_string())
+                        .unwrap_or_else(|| format!("Batch {}", batch.id));
 
-                    self.entrys.push(entry);
-                    self.entrys.sort();
+                    row.col(|ui| {
+                        let label = egui::Label::new(
+                            RichText::new(format!("{arrow} {archived_at}")).strong(),
+                        )
+                        .sense(Sense::click());
 
-                    self.description.clear();
-                    self.start_time.clear();
-                    self.end_time.clear();
+                        if ui.add(label).clicked() {
+                            toggled = Some(batch.id);
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.strong(format_hours(batch.minutes));
+                    });
+                    row.col(|ui| {
+                        ui.strong(format_hours(gross_minutes(&batch.entrys)));
+                    });
+                    row.col(|ui| {
+                        let billed = batch.billed(&self.settings.rounding);
+                        billed_label(ui, billed, batch.minutes);
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{} entrys", batch.count));
+                    });
+                    row.col(|_| {});
+                    row.col(|ui| {
+                        ui.strong(batch.title());
+                    });
+                }
+                Some(&ArchiveRow::Entry(i, j)) => {
+                    entry_row(&mut row, &self.archive[i].entrys[j], &self.settings)
+                }
+                // Show total hours in the last row
+                None => {
+                    row.col(|_| {});
+                    row.col(|ui| {
+                        ui.label(format_hours(self.total_minutes_archive));
+                    });
+                    row.col(|ui| {
+                        let entrys = self.archive.iter().flat_map(|batch| &batch.entrys);
+                        ui.label(format_hours(entrys.map(Entry::gross_minutes).sum()));
+                    });
+                    row.col(|ui| {
+                        // Each batch is its own billing period
+                        let billed = self
+                            .archive
+                            .iter()
+                            .map(|batch| batch.billed(&self.settings.rounding))
+                            .sum();
+                        billed_label(ui, billed, self.total_minutes_archive);
+                    });
                 }
             }
         });
+
+        toggled
     }
+}
 
-    fn body(&self, body: egui_extras::TableBody, entrys: &Vec<Entry>, total_hours: f64) {
-        let total_rows = entrys.len();
+// Minutes from start to end of all the entrys, breaks included
+fn gross_minutes(entrys: &[Entry]) -> i64 {
+    entrys.iter().map(Entry::gross_minutes).sum()
+}
 
-        if total_rows == 0 {
-            return;
-        }
+// Billed hours, with how much rounding changed them on hover
+fn billed_label(ui: &mut egui::Ui, billed: i64, minutes: i64) {
+    let response = ui.label(format_hours(billed));
 
-        body.rows(18.0, total_rows + 1, |mut row| {
-            let row_index = row.index();
+    if billed != minutes {
+        let sign = if billed > minutes { "+" } else { "-" };
 
-            // Show total hours in the last row
-            if row_index == total_rows {
-                row.col(|_| {});
-                row.col(|ui| {
-                    ui.label(total_hours.to_string());
-                });
-            } else {
-                // Is safe as we are in the range 0..entrys.len()
-                let entry = unsafe { entrys.get_unchecked(row_index) };
+        response.on_hover_text(format!(
+            "{sign}{} hours from rounding {} hours worked",
+            format_hours((billed - minutes).abs()),
+            format_hours(minutes)
+        ));
+    }
+}
 
-                if entry.date.is_empty() {
-                    if row_index != total_rows - 1 {
-                        row.col(|_| {});
-                    }
-                    return;
+// Counts up while it runs, in the zone it was started in unless
+// `local_times` is set
+fn timer_row(row: &mut TableRow, timer: &Timer, settings: &Settings) {
+    let zone = if settings.local_times {
+        local_zone()
+    } else {
+        timer.zone
+    };
+    let started = timer.started.with_timezone(&zone);
+
+    row.col(|ui| {
+        ui.strong(started.format("%Y-%m-%d").to_string());
+    });
+    row.col(|ui| {
+        ui.strong(format_elapsed(timer.elapsed(Utc::now())));
+    });
+    row.col(|_| {});
+    row.col(|_| {});
+    row.col(|ui| {
+        ui.label(started.format("%H:%M").to_string())
+            .on_hover_text(zone.name());
+    });
+    row.col(|ui| {
+        ui.weak("running");
+    });
+    row.col(|ui| {
+        ui.label(&timer.description);
+    });
+}
+
+// Time nothing was logged for, clicked to fill it in
+fn gap_row(row: &mut TableRow, gap: &Gap) {
+    let ghost = |ui: &mut egui::Ui, text: String| {
+        ui.label(RichText::new(text).weak().italics());
+    };
+
+    row.col(|ui| ghost(ui, gap.date.format("%Y-%m-%d").to_string()));
+    row.col(|ui| ghost(ui, format_hours(gap.minutes())));
+    row.col(|_| {});
+    row.col(|_| {});
+    row.col(|ui| ghost(ui, format_time(Some(gap.start))));
+    row.col(|ui| ghost(ui, format_time(Some(gap.end))));
+    row.col(|ui| ghost(ui, "Nothing logged, click to fill in".to_string()));
+}
+
+// A whole hour of the day
+fn hour(hour: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN)
+}
+
+enum ArchiveRow {
+    Batch(usize),
+    // An entry of an opened batch
+    Entry(usize, usize),
+}
+
+// Times are shown in the current zone rather than the entry's with the
+// `local_times` setting
+fn entry_row(row: &mut TableRow, entry: &Entry, settings: &Settings) {
+    let shown;
+    let entry = if settings.local_times {
+        shown = entry.in_zone(local_zone());
+        &shown
+    } else {
+        entry
+    };
+
+    row.col(|ui| {
+        ui.label(entry.date.format("%Y-%m-%d").to_string());
+    });
+    row.col(|ui| {
+        let response = ui.label(format_hours(entry.minutes));
+
+        // Show how an entry past midnight splits between days
+        let days = entry.minutes_by_day();
+
+        if days.len() > 1 {
+            response.on_hover_ui(|ui| {
+                for (date, minutes) in days {
+                    ui.label(format!("{date}: {}", format_hours(minutes)));
                 }
+            });
+        }
+    });
+    row.col(|ui| {
+        let response = ui.label(format_hours(entry.gross_minutes()));
 
-                row.col(|ui| {
-                    ui.label(&entry.date);
-                });
-                row.col(|ui| {
-                    ui.label(entry.hours.to_string());
-                });
-                row.col(|ui| {
-                    ui.label(&entry.start);
-                });
-                row.col(|ui| {
-                    ui.label(&entry.end);
-                });
-                row.col(|ui| {
-                    ui.label(&entry.description);
-                });
-            }
-        });
-    }
+        if entry.break_minutes > 0 {
+            response.on_hover_text(format!(
+                "{} hours unpaid break",
+                format_hours(entry.break_minutes)
+            ));
+        }
+    });
+    row.col(|ui| {
+        // Only entrys are rounded one by one, otherwise it is their totals
+        if settings.rounding.scope == rounding::Scope::Entry {
+            billed_label(ui, entry.billed_minutes, entry.minutes);
+        }
+    });
+    row.col(|ui| {
+        let response = ui.label(format_time(entry.start));
+
+        if entry.start.is_some() {
+            response.on_hover_text(entry.zone.name());
+        }
+    });
+    row.col(|ui| {
+        let end = format_time(entry.end);
+
+        match entry.days_later() {
+            0 => ui.label(end),
+            1 => ui.label(format!("{end} +1 day")),
+            days => ui.label(format!("{end} +{days} days")),
+        };
+    });
+    row.col(|ui| {
+        ui.label(&entry.description);
+    });
 }
 
 impl eframe::App for WorkTracker {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Keep the elapsed time ticking
+        if self.timer.is_some() || self.focus.is_some() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+
+        self.focus_tick(ctx);
+
         self.top_input(ctx);
 
         egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
             ui.add_space(7.0);
 
             ui.horizontal(|ui| {
+                let can_archive =
+                    !self.viewing_archive && !self.read_only && !self.entrys.is_empty();
+
                 if ui
-                    .add_enabled(!self.viewing_archive, egui::Button::new("Archive all"))
+                    .add_enabled(can_archive, egui::Button::new("Archive all"))
                     .clicked()
                 {
-                    self.entrys.sort();
-                    if write_to_archive(&self.entrys).is_ok() {
-                        self.entrys.clear();
-                        wipe_entrys();
-                    }
+                    self.archive_all();
                 };
 
+                ui.add_enabled(
+                    can_archive,
+                    egui::TextEdit::singleline(&mut self.archive_label)
+                        .hint_text("Label (optional)")
+                        .desired_width(150.0),
+                );
+
                 let text = if self.viewing_archive {
                     "Close archive"
                 } else {
                     "Open archive"
                 };
 
+                if ui.button("Templates").clicked() {
+                    self.templates_open = !self.templates_open;
+                }
+
+                if ui.button("Overlaps").clicked() {
+                    self.overlaps_open = !self.overlaps_open;
+
+                    if self.overlaps_open {
+                        self.report_overlaps();
+                    }
+                }
+
+                if ui.button("Settings").clicked() {
+                    self.settings_open = !self.settings_open;
+                }
+
+                if ui
+                    .add_enabled(self.data_dir.is_some(), egui::Button::new("Backups"))
+                    .clicked()
+                {
+                    self.backups_open = !self.backups_open;
+
+                    if self.backups_open {
+                        self.load_snapshots();
+                    }
+                }
+
                 if ui.button(text).clicked() {
                     self.viewing_archive = if self.viewing_archive {
                         false
                     } else {
-                        if self.archive.len() == 0 {
-                            read_archive(&mut self.archive, &mut self.total_hours_archive).ok();
+                        if self.archive.is_empty() {
+                            self.read_archive();
                         }
                         true
                     }
@@ -223,13 +1856,77 @@ impl eframe::App for WorkTracker {
             ui.add_space(3.0);
         });
 
+        let mut settings_open = self.settings_open;
+
+        egui::Window::new("Settings")
+            .open(&mut settings_open)
+            .resizable(false)
+            .show(ctx, |ui| self.settings_window(ui));
+
+        self.settings_open = settings_open;
+
+        let mut backups_open = self.backups_open;
+
+        egui::Window::new("Restore backup")
+            .open(&mut backups_open)
+            .resizable(false)
+            .show(ctx, |ui| self.backups_window(ui));
+
+        self.backups_open = backups_open;
+
+        let mut overlaps_open = self.overlaps_open;
+
+        egui::Window::new("Overlapping entrys")
+            .open(&mut overlaps_open)
+            .resizable(false)
+            .show(ctx, |ui| self.overlaps_window(ui));
+
+        self.overlaps_open = overlaps_open;
+
+        let mut templates_open = self.templates_open;
+
+        egui::Window::new("Templates")
+            .open(&mut templates_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(!self.read_only, |ui| self.templates_window(ui));
+            });
+
+        self.templates_open = templates_open;
+
+        let mut editing_open = self.editing.is_some();
+
+        egui::Window::new("Edit entry")
+            .open(&mut editing_open)
+            .resizable(false)
+            .show(ctx, |ui| self.edit_window(ui));
+
+        if !editing_open {
+            self.editing = None;
+        }
+
+        let mut confirming_open = self.confirming.is_some();
+
+        egui::Window::new("Long entry")
+            .open(&mut confirming_open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| self.confirm_window(ui));
+
+        if !confirming_open {
+            self.confirming = None;
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             let table = TableBuilder::new(ui)
+                .sense(Sense::click())
                 .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
                 .column(Column::exact(75.0))
                 .column(Column::exact(45.0))
+                .column(Column::exact(45.0))
+                .column(Column::exact(45.0))
                 .column(Column::exact(60.0))
-                .column(Column::exact(60.0))
+                .column(Column::exact(95.0))
                 .column(Column::remainder());
 
             table
@@ -245,6 +1942,18 @@ impl eframe::App for WorkTracker {
                     header.col(|ui| {
                         ui.label("Hours");
                     });
+                    header
+                        .col(|ui| {
+                            ui.label("Gross");
+                        })
+                        .1
+                        .on_hover_text("Hours including unpaid breaks");
+                    header
+                        .col(|ui| {
+                            ui.label("Billed");
+                        })
+                        .1
+                        .on_hover_text("Hours after rounding");
                     header.col(|ui| {
                         ui.label("Start");
                     });
@@ -257,9 +1966,38 @@ impl eframe::App for WorkTracker {
                 })
                 .body(|body| {
                     if self.viewing_archive {
-                        self.body(body, &self.archive, self.total_hours_archive);
+                        if let Some(id) = self.archive_body(body) {
+                            if !self.expanded.remove(&id) {
+                                self.expanded.insert(id);
+                            }
+                        }
                     } else {
-                        self.body(body, &self.entrys, self.total_hours);
+                        match self.body(body) {
+                            Some(RowAction::Edit(index)) => {
+                                let entry = &self.entrys[index];
+
+                                self.editing = Some(Editing {
+                                    id: entry.id,
+                                    form: EntryForm::from_entry(entry),
+                                });
+                            }
+                            Some(RowAction::Delete(index)) => {
+                                self.update_entry(self.entrys[index].id, None);
+                            }
+                            Some(RowAction::Fill(index)) => {
+                                let gap = self.gaps[index];
+
+                                // Whatever description was typed is kept
+                                self.form = EntryForm {
+                                    date: gap.date,
+                                    description: std::mem::take(&mut self.form.description),
+                                    start_time: format_time(Some(gap.start)),
+                                    end_time: format_time(Some(gap.end)),
+                                    ..EntryForm::new(gap.date)
+                                };
+                            }
+                            None => {}
+                        }
                     }
                 });
 
@@ -267,13 +2005,91 @@ impl eframe::App for WorkTracker {
             // ui.horizontal(|ui| {
             //     ui.label("Total Hours");
             //     ui.add_space(6.0);
-            //     ui.label(self.total_hours.to_string());
+            //     ui.label(self.total_minutes.to_string());
             // });
         });
     }
 }
 
+#[derive(Default)]
+struct Args {
+    ephemeral: bool,
+    sqlite: bool,
+    data_dir: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--ephemeral" => args.ephemeral = true,
+                "--sqlite" => args.sqlite = true,
+                "--data-dir" => args.data_dir = iter.next().map(PathBuf::from),
+                _ => {
+                    if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                        args.data_dir = Some(PathBuf::from(dir));
+                    }
+                }
+            }
+        }
+
+        args
+    }
+}
+
 fn main() -> Result<(), eframe::Error> {
+    let args = Args::parse();
+    let settings = Settings::load();
+
+    let data_dir = settings.data_dir(args.data_dir);
+    let mut errors = Vec::new();
+
+    let mut read_only = false;
+
+    let lock = match &data_dir {
+        Some(dir) if !args.ephemeral => match storage::lock(dir) {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => {
+                read_only = true;
+                None
+            }
+            Err(e) => {
+                errors.push(format!("Couldn't lock the data directory: {e}"));
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // Ephemeral sessions start empty and are thrown away on exit
+    let storage: Box<dyn Storage> = match &data_dir {
+        _ if args.ephemeral => Box::new(MemoryStorage::default()),
+        Some(dir) => storage::open(
+            dir.clone(),
+            args.sqlite,
+            read_only,
+            settings.backups,
+            &mut errors,
+        ),
+        None => {
+            errors.push(
+                "No data directory could be found so nothing will be saved. \
+                Choose one in Settings or start Hours with --data-dir."
+                    .to_string(),
+            );
+            Box::new(MemoryStorage::default())
+        }
+    };
+
+    let title = if args.ephemeral {
+        "Hours (ephemeral)"
+    } else {
+        "Hours"
+    };
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             // .with_maximized(true)
@@ -287,8 +2103,162 @@ fn main() -> Result<(), eframe::Error> {
     };
 
     eframe::run_native(
-        "Hours",
+        title,
         options,
-        Box::new(|cc| Ok(Box::new(WorkTracker::new(cc)))),
+        Box::new(|cc| {
+            let data_dir_input = settings
+                .data_dir
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+
+            let mut app = WorkTracker::new(cc, storage, settings);
+
+            app.data_dir_input = data_dir_input;
+            app.data_dir = data_dir.filter(|_| !args.ephemeral);
+            match app.data_dir.as_deref().map(template::load) {
+                Some(Ok(templates)) => app.templates = templates,
+                Some(Err(e)) => {
+                    errors.push(format!("Couldn't read the templates: {e}"));
+                    app.templates_unread = true;
+                }
+                None => {}
+            }
+            app._lock = lock;
+            app.read_only = read_only;
+            // Before any from reading the entrys
+            app.errors.splice(0..0, errors);
+
+            Ok(Box::new(app))
+        }),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(day: u32, description: &str, minutes: i64) -> Entry {
+        let date = NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        Entry::new(
+            date,
+            description.to_string(),
+            None,
+            None,
+            date,
+            chrono_tz::Tz::UTC,
+            minutes,
+        )
+    }
+
+    #[test]
+    fn adds_edits_deletes_and_archives() {
+        let mut app =
+            WorkTracker::with_storage(Box::new(MemoryStorage::default()), Settings::default());
+
+        let first = entry(4, "Planning", 90);
+        let second = entry(5, "Review", 60);
+
+        assert!(app.add_entry(first.clone()));
+        assert!(app.add_entry(second.clone()));
+        assert_eq!(app.total_minutes, 150);
+
+        let edited = Entry {
+            minutes: 120,
+            billed_minutes: 120,
+            ..second.clone()
+        };
+        assert!(app.update_entry(second.id, Some(edited)));
+        assert_eq!(app.total_minutes, 210);
+
+        // What was saved reads back the same
+        app.reload();
+        let read: Vec<_> = app
+            .entrys
+            .iter()
+            .map(|entry| (entry.description.as_str(), entry.minutes))
+            .collect();
+        assert_eq!(read, [("Review", 120), ("Planning", 90)]);
+        assert_eq!(app.total_minutes, 210);
+
+        assert!(app.update_entry(first.id, None));
+        assert_eq!(app.entrys.len(), 1);
+        assert_eq!(app.total_minutes, 120);
+
+        // Nothing changes for an entry that isn't there
+        assert!(!app.update_entry(first.id, None));
+        assert_eq!(app.total_minutes, 120);
+        assert_eq!(app.errors.len(), 1);
+        app.errors.clear();
+
+        app.archive_label = "March".to_string();
+        app.archive_all();
+        assert!(app.errors.is_empty(), "{:?}", app.errors);
+        assert!(app.entrys.is_empty());
+        assert_eq!(app.total_minutes, 0);
+        assert!(app.archive_label.is_empty());
+
+        app.read_archive();
+        let batches: Vec<_> = app
+            .archive
+            .iter()
+            .map(|batch| (batch.label.as_str(), batch.count, batch.minutes))
+            .collect();
+        assert_eq!(batches, [("March", 1, 120)]);
+        assert_eq!(app.total_minutes_archive, 120);
+
+        // Billed as rounded when archived, whatever the rounding is now
+        app.settings.rounding = rounding::Rounding {
+            mode: rounding::Mode::Up,
+            minutes: 45,
+            scope: rounding::Scope::Period,
+        };
+        assert_eq!(app.archive[0].billed(&app.settings.rounding), 120);
+
+        app.reload();
+        assert!(app.entrys.is_empty());
+    }
+
+    #[test]
+    fn counts_archived_entrys_in_gaps_and_overlaps() {
+        let mut app =
+            WorkTracker::with_storage(Box::new(MemoryStorage::default()), Settings::default());
+
+        let date = Local::now().date_naive();
+        let timed = |start: u32, end: u32| {
+            Entry::new(
+                date,
+                String::new(),
+                Some(hour(start)),
+                Some(hour(end)),
+                date,
+                local_zone(),
+                i64::from(end - start) * 60,
+            )
+        };
+
+        assert!(app.add_entry(timed(9, 10)));
+        assert!(app.add_entry(timed(11, 13)));
+        app.archive_all();
+
+        // The archived entrys fill the day around it
+        assert!(app.add_entry(timed(12, 14)));
+        assert!(app.errors.is_empty(), "{:?}", app.errors);
+
+        let gaps: Vec<_> = app.gaps.iter().map(|gap| (gap.start, gap.end)).collect();
+        assert_eq!(gaps, [(hour(10), hour(11))]);
+
+        app.report_overlaps();
+        let overlaps: Vec<_> = app
+            .overlap_report
+            .iter()
+            .map(|overlap| overlap.minutes)
+            .collect();
+        assert_eq!(overlaps, [60]);
+        assert_eq!(
+            app.find_entry(app.overlap_report[0].first)
+                .map(|(_, found_in)| found_in),
+            Some("Archived".to_string())
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::entry::Entry;
+
+// Two entrys that were both being worked on at once, `first` being the one
+// that starts first
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Overlap {
+    pub first: Uuid,
+    pub second: Uuid,
+    // How long they share
+    pub minutes: i64,
+}
+
+// Every pair of entrys that overlap, in the order they start. Entrys of just
+// a duration never do as when they were isn't known.
+pub fn find<'a>(entrys: impl IntoIterator<Item = &'a Entry>, overlaps: &mut Vec<Overlap>) {
+    let mut spans: Vec<_> = entrys
+        .into_iter()
+        .filter_map(|entry| Some((entry.starts_at()?, entry.ends_at()?, entry.id)))
+        .collect();
+
+    // Instants compare the same whatever zone they are in
+    spans.sort_by_key(|&(start, end, _)| (start, end));
+
+    for (i, &(_, end, first)) in spans.iter().enumerate() {
+        for &(other_start, other_end, second) in &spans[i + 1..] {
+            if other_start >= end {
+                break;
+            }
+
+            overlaps.push(Overlap {
+                first,
+                second,
+                minutes: (end.min(other_end) - other_start).num_minutes(),
+            });
+        }
+    }
+}
+
+// `second` starting when `first` ends instead. None when it is within
+// `first` or its break wouldn't fit in what is left.
+pub fn trim(first: &Entry, second: &Entry) -> Option<Entry> {
+    spanning(second, first.ends_at()?, second.ends_at()?)
+}
+
+// Both as one entry from the first start to the last end, keeping the id
+// and zone of `first` and both breaks
+pub fn merge(first: &Entry, second: &Entry) -> Option<Entry> {
+    let from = first.starts_at()?.min(second.starts_at()?);
+    let to = first.ends_at()?.max(second.ends_at()?);
+
+    let description = if second.description.is_empty() || second.description == first.description {
+        first.description.clone()
+    } else if first.description.is_empty() {
+        second.description.clone()
+    } else {
+        format!("{}; {}", first.description, second.description)
+    };
+
+    let merged = Entry {
+        description,
+        break_minutes: first.break_minutes + second.break_minutes,
+        ..first.clone()
+    };
+
+    spanning(&merged, from, to)
+}
+
+// The entry moved to run from `from` until `to`, written in its own zone.
+// Billed minutes are left for the caller to round.
+fn spanning(entry: &Entry, from: DateTime<Tz>, to: DateTime<Tz>) -> Option<Entry> {
+    let minutes = (to - from).num_minutes() - entry.break_minutes;
+
+    if minutes < 1 {
+        return None;
+    }
+
+    let (from, to) = (
+        from.with_timezone(&entry.zone),
+        to.with_timezone(&entry.zone),
+    );
+
+    Some(Entry {
+        date: from.date_naive(),
+        start: Some(from.time()),
+        end: Some(to.time()),
+        end_date: to.date_naive(),
+        minutes,
+        billed_minutes: minutes,
+        ..entry.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+
+    fn entry(zone: &str, start: (u32, u32), end: (u32, u32)) -> Entry {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let start = NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap();
+        let end = NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap();
+
+        Entry::new(
+            date,
+            String::new(),
+            Some(start),
+            Some(end),
+            date,
+            zone.parse().unwrap(),
+            (end - start).num_minutes(),
+        )
+    }
+
+    #[test]
+    fn finds_overlaps() {
+        let utc = "UTC";
+
+        let cases = [
+            (
+                entry(utc, (9, 0), (12, 0)),
+                entry(utc, (11, 0), (13, 0)),
+                Some(60),
+            ),
+            (
+                entry(utc, (11, 0), (13, 0)),
+                entry(utc, (9, 0), (12, 0)),
+                Some(60),
+            ),
+            (
+                entry(utc, (9, 0), (17, 0)),
+                entry(utc, (12, 0), (13, 0)),
+                Some(60),
+            ),
+            (
+                entry(utc, (9, 0), (12, 0)),
+                entry(utc, (12, 0), (13, 0)),
+                None,
+            ),
+            (
+                entry(utc, (9, 0), (10, 0)),
+                entry(utc, (14, 0), (15, 0)),
+                None,
+            ),
+            // 10:00 in Berlin is 09:00 in UTC in winter
+            (
+                entry(utc, (9, 0), (10, 0)),
+                entry("Europe/Berlin", (10, 30), (11, 30)),
+                Some(30),
+            ),
+        ];
+
+        for (a, b, expected) in cases {
+            let mut overlaps = Vec::new();
+            find([&a, &b], &mut overlaps);
+
+            let minutes = overlaps.first().map(|overlap| overlap.minutes);
+            assert_eq!(minutes, expected, "{:?} - {:?}", a.start, b.start);
+        }
+    }
+
+    #[test]
+    fn trims_and_merges() {
+        let first = entry("UTC", (9, 0), (12, 0));
+        let second = Entry {
+            break_minutes: 15,
+            ..entry("UTC", (11, 0), (13, 0))
+        };
+
+        let trimmed = trim(&first, &second).unwrap();
+        assert_eq!(trimmed.start, NaiveTime::from_hms_opt(12, 0, 0));
+        assert_eq!(trimmed.minutes, 45);
+        assert_eq!(trimmed.id, second.id);
+
+        let merged = merge(&first, &second).unwrap();
+        assert_eq!(merged.start, NaiveTime::from_hms_opt(9, 0, 0));
+        assert_eq!(merged.end, NaiveTime::from_hms_opt(13, 0, 0));
+        assert_eq!(merged.minutes, 225);
+        assert_eq!(merged.id, first.id);
+
+        // Nothing is left of one entry within another
+        let within = entry("UTC", (10, 0), (11, 0));
+        assert!(trim(&first, &within).is_none());
+    }
+}
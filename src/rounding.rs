@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use crate::entry::Entry;
+
+// How billed time is rounded from the time worked. There is one for everything
+// as entrys have no project to round differently by.
+#[derive(Clone, Copy)]
+pub struct Rounding {
+    pub mode: Mode,
+    // The increment rounded to, like 6 or 15
+    pub minutes: i64,
+    pub scope: Scope,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    None,
+    Nearest,
+    Up,
+    Down,
+}
+
+// What gets rounded. Entrys keep the billed minutes they were saved with, day
+// and period totals are rounded when they are shown and kept with the batch
+// once archived.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Entry,
+    Day,
+    Period,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Rounding {
+            mode: Mode::None,
+            minutes: 15,
+            scope: Scope::Entry,
+        }
+    }
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [Mode::None, Mode::Nearest, Mode::Up, Mode::Down];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::None => "none",
+            Mode::Nearest => "nearest",
+            Mode::Up => "up",
+            Mode::Down => "down",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mode> {
+        Mode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Entry, Scope::Day, Scope::Period];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Entry => "entry",
+            Scope::Day => "day",
+            Scope::Period => "period",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|scope| scope.name() == name)
+    }
+}
+
+impl Rounding {
+    pub fn round(&self, minutes: i64) -> i64 {
+        let step = self.minutes.max(1);
+
+        match self.mode {
+            Mode::None => minutes,
+            Mode::Nearest => (minutes + step / 2).div_euclid(step) * step,
+            Mode::Up => (minutes + step - 1).div_euclid(step) * step,
+            Mode::Down => minutes.div_euclid(step) * step,
+        }
+    }
+
+    // The billed minutes to save with a new or changed entry
+    pub fn entry(&self, minutes: i64) -> i64 {
+        match self.scope {
+            Scope::Entry => self.round(minutes),
+            Scope::Day | Scope::Period => minutes,
+        }
+    }
+
+    // The billed minutes of the entrys taken as one period
+    pub fn total(&self, entrys: &[Entry]) -> i64 {
+        match self.scope {
+            Scope::Entry => entrys.iter().map(|entry| entry.billed_minutes).sum(),
+            Scope::Day => {
+                let mut days = BTreeMap::new();
+
+                // Entrys past midnight count towards each day they are on
+                for entry in entrys {
+                    for (date, minutes) in entry.minutes_by_day() {
+                        *days.entry(date).or_insert(0) += minutes;
+                    }
+                }
+
+                days.into_values().map(|minutes| self.round(minutes)).sum()
+            }
+            Scope::Period => self.round(entrys.iter().map(|entry| entry.minutes).sum()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+    use chrono_tz::Tz;
+
+    use super::*;
+
+    #[test]
+    fn rounds_each_day() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let time = |hour: u32, minute: u32| NaiveTime::from_hms_opt(hour, minute, 0);
+
+        let rounding = Rounding {
+            mode: Mode::Nearest,
+            minutes: 15,
+            scope: Scope::Day,
+        };
+
+        // Five minutes on each side of midnight is too little to bill either day
+        let overnight = Entry::new(
+            date(4),
+            String::new(),
+            time(23, 55),
+            time(0, 5),
+            date(5),
+            Tz::UTC,
+            10,
+        );
+        assert_eq!(rounding.total(std::slice::from_ref(&overnight)), 0);
+
+        // Unless there is more on the day
+        let next = Entry::new(date(5), String::new(), None, None, date(5), Tz::UTC, 5);
+        assert_eq!(rounding.total(&[overnight, next]), 15);
+    }
+
+    #[test]
+    fn rounds_to_increments() {
+        let rounding = |mode, minutes| Rounding {
+            mode,
+            minutes,
+            scope: Scope::Entry,
+        };
+
+        let cases = [
+            (Mode::None, 15, 52, 52),
+            (Mode::Nearest, 15, 52, 45),
+            (Mode::Nearest, 15, 53, 60),
+            (Mode::Nearest, 6, 3, 6),
+            (Mode::Nearest, 6, 2, 0),
+            (Mode::Up, 15, 46, 60),
+            (Mode::Up, 15, 45, 45),
+            (Mode::Up, 6, 1, 6),
+            (Mode::Down, 15, 59, 45),
+            (Mode::Down, 6, 65, 60),
+            (Mode::Nearest, 0, 7, 7),
+        ];
+
+        for (mode, step, minutes, expected) in cases {
+            assert_eq!(
+                rounding(mode, step).round(minutes),
+                expected,
+                "{} to {step} of {minutes}",
+                mode.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::env;
+use std::fs::{create_dir_all, read};
+use std::io;
+use std::path::PathBuf;
+
+use dirs_next::config_dir;
+
+use crate::backup::Retention;
+use crate::csv;
+use crate::rounding::{self, Rounding};
+use crate::utils::TimeDefaults;
+
+// Settings kept between runs as `key,value` records in the config directory
+pub struct Settings {
+    // Where entrys and the archive are kept instead of the config directory
+    pub data_dir: Option<PathBuf>,
+    pub backups: Retention,
+    pub times: TimeDefaults,
+    // Entrys longer than this many hours need confirming, 0 never asks
+    pub confirm_over_hours: u32,
+    // Shows every entry in the current time zone instead of its own
+    pub local_times: bool,
+    pub rounding: Rounding,
+    // Lengths of the work and breaks in a focus session
+    pub focus_minutes: u32,
+    pub focus_break_minutes: u32,
+    // Plays the system alert sound when a work or break is over
+    pub focus_sound: bool,
+    // Gaps between entrys are shown for this part of the day, in whole hours
+    pub show_gaps: bool,
+    pub work_from: u32,
+    pub work_until: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            data_dir: None,
+            backups: Retention::default(),
+            times: TimeDefaults::default(),
+            confirm_over_hours: 16,
+            local_times: false,
+            rounding: Rounding::default(),
+            focus_minutes: 25,
+            focus_break_minutes: 5,
+            focus_sound: true,
+            show_gaps: true,
+            work_from: 9,
+            work_until: 17,
+        }
+    }
+}
+
+// The settings always live in the config directory so they can point the data elsewhere
+fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|path| path.join("hours").join("settings.csv"))
+}
+
+pub fn default_data_dir() -> Option<PathBuf> {
+    config_dir().map(|path| path.join("hours"))
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings = Settings::default();
+
+        let bytes = match settings_path().map(read) {
+            Some(Ok(bytes)) => bytes,
+            _ => return settings,
+        };
+
+        for record in csv::parse(&String::from_utf8_lossy(&bytes)) {
+            let (key, value) = match record.as_slice() {
+                [key, value] => (key.as_str(), value.as_str()),
+                _ => continue,
+            };
+
+            match key {
+                "data_dir" if !value.is_empty() => settings.data_dir = Some(PathBuf::from(value)),
+                "backup_keep" => {
+                    if let Ok(keep) = value.parse() {
+                        settings.backups.keep = keep;
+                    }
+                }
+                "backup_max_age_days" => {
+                    if let Ok(days) = value.parse() {
+                        settings.backups.max_age_days = days;
+                    }
+                }
+                "pm_before" => {
+                    if let Ok(hour) = value.parse() {
+                        settings.times.pm_before = hour;
+                    }
+                }
+                "confirm_over_hours" => {
+                    if let Ok(hours) = value.parse() {
+                        settings.confirm_over_hours = hours;
+                    }
+                }
+                "local_times" => settings.local_times = value == "true",
+                "rounding" => {
+                    if let Some(mode) = rounding::Mode::from_name(value) {
+                        settings.rounding.mode = mode;
+                    }
+                }
+                "rounding_minutes" => {
+                    if let Ok(minutes) = value.parse() {
+                        settings.rounding.minutes = minutes;
+                    }
+                }
+                "rounding_scope" => {
+                    if let Some(scope) = rounding::Scope::from_name(value) {
+                        settings.rounding.scope = scope;
+                    }
+                }
+                "focus_minutes" => {
+                    if let Ok(minutes) = value.parse() {
+                        settings.focus_minutes = minutes;
+                    }
+                }
+                "focus_break_minutes" => {
+                    if let Ok(minutes) = value.parse() {
+                        settings.focus_break_minutes = minutes;
+                    }
+                }
+                "focus_sound" => settings.focus_sound = value == "true",
+                "show_gaps" => settings.show_gaps = value == "true",
+                "work_from" => {
+                    if let Ok(hour) = value.parse() {
+                        settings.work_from = hour;
+                    }
+                }
+                "work_until" => {
+                    if let Ok(hour) = value.parse() {
+                        settings.work_until = hour;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), io::Error> {
+        let path = settings_path().ok_or_else(|| io::Error::other("Config dir not found"))?;
+
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+
+        let data_dir = self
+            .data_dir
+            .as_ref()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let records = [
+            ["data_dir".to_string(), data_dir],
+            ["backup_keep".to_string(), self.backups.keep.to_string()],
+            [
+                "backup_max_age_days".to_string(),
+                self.backups.max_age_days.to_string(),
+            ],
+            ["pm_before".to_string(), self.times.pm_before.to_string()],
+            [
+                "confirm_over_hours".to_string(),
+                self.confirm_over_hours.to_string(),
+            ],
+            ["local_times".to_string(), self.local_times.to_string()],
+            [
+                "rounding".to_string(),
+                self.rounding.mode.name().to_string(),
+            ],
+            [
+                "rounding_minutes".to_string(),
+                self.rounding.minutes.to_string(),
+            ],
+            [
+                "rounding_scope".to_string(),
+                self.rounding.scope.name().to_string(),
+            ],
+            ["focus_minutes".to_string(), self.focus_minutes.to_string()],
+            [
+                "focus_break_minutes".to_string(),
+                self.focus_break_minutes.to_string(),
+            ],
+            ["focus_sound".to_string(), self.focus_sound.to_string()],
+            ["show_gaps".to_string(), self.show_gaps.to_string()],
+            ["work_from".to_string(), self.work_from.to_string()],
+            ["work_until".to_string(), self.work_until.to_string()],
+        ];
+
+        csv::replace_records(&path, &records)
+    }
+
+    // The --data-dir argument wins, then HOURS_DATA_DIR, then the setting and
+    // finally the config directory
+    pub fn data_dir(&self, arg: Option<PathBuf>) -> Option<PathBuf> {
+        arg.or_else(|| {
+            env::var_os("HOURS_DATA_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        })
+        .or_else(|| self.data_dir.clone())
+        .or_else(default_data_dir)
+    }
+}
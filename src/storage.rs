@@ -0,0 +1,330 @@
+use std::fs::{create_dir_all, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::backup::{self, Retention};
+use crate::batch::Batch;
+use crate::entry::Entry;
+use crate::timer::Timer;
+
+pub use self::csv::CsvStorage;
+pub use self::error::StorageError;
+pub use self::memory::MemoryStorage;
+pub use self::sqlite::SqliteStorage;
+
+mod csv;
+mod error;
+mod memory;
+mod sqlite;
+
+// Where entrys and the archive are persisted. The archive is made of batches,
+// one for each "Archive all", holding the entrys archived then.
+pub trait Storage {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError>;
+
+    fn write_entry(&mut self, entry: &Entry) -> Result<(), StorageError>;
+
+    // Replaces the current entry with the same id
+    fn update_entry(&mut self, entry: &Entry) -> Result<(), StorageError>;
+
+    fn delete_entry(&mut self, id: Uuid) -> Result<(), StorageError>;
+
+    // Batches oldest first
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError>;
+
+    // Adds the entrys to the archive as a new batch with an optional label and
+    // what they were billed, and removes all current entrys. Either both
+    // happen or neither does.
+    fn archive_entrys(
+        &mut self,
+        entrys: &[Entry],
+        label: &str,
+        billed_minutes: i64,
+    ) -> Result<(), StorageError>;
+
+    // The timer left running, if there is one
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError>;
+
+    // Saves the running timer, or forgets it with None
+    fn write_timer(&mut self, timer: Option<&Timer>) -> Result<(), StorageError>;
+
+    // Current and archived entrys dated from `from` to `to` inclusive, newest
+    // first
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError>;
+}
+
+// Takes the lock on the data directory that is held for as long as Hours is
+// running. None means another copy already has it.
+pub fn lock(dir: &Path) -> Result<Option<File>, io::Error> {
+    create_dir_all(dir)?;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(dir.join("hours.lock"))?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+// Uses the SQLite database if asked to or if one already exists, otherwise
+// the csv files. A new database starts with everything from the csv files.
+// When read only nothing is recovered, migrated or created and every change is
+// refused, otherwise the data is snapshotted before anything is changed.
+// Anything that went wrong along the way is added to `errors`.
+pub fn open(
+    dir: PathBuf,
+    sqlite: bool,
+    read_only: bool,
+    retention: Retention,
+    errors: &mut Vec<String>,
+) -> Box<dyn Storage> {
+    if read_only {
+        let storage = open_snapshot(&dir).unwrap_or_else(|e| {
+            errors.push(format!("Couldn't open the database: {e}"));
+            Box::new(CsvStorage::read_only(dir))
+        });
+
+        return Box::new(ReadOnly(storage));
+    }
+
+    let csv = CsvStorage::new(dir.clone());
+
+    if let Err(e) = csv.recover() {
+        errors.push(format!("Couldn't finish archiving: {e}"));
+    }
+
+    match csv.needs_migration() {
+        Ok(true) => {
+            if let Err(e) = backup::snapshot(&dir, retention) {
+                errors.push(format!("Couldn't back up before migrating: {e}"));
+            }
+        }
+        Ok(false) => {}
+        Err(e) => errors.push(format!("Couldn't check the data format: {e}")),
+    }
+
+    let storage: Box<dyn Storage> = match csv.path("hours.db") {
+        Ok(path) if sqlite || path.exists() => {
+            let storage = if path.exists() {
+                SqliteStorage::open(&path)
+            } else {
+                SqliteStorage::create(&path, &csv)
+            };
+
+            match storage {
+                Ok(storage) => Box::new(storage),
+                Err(e) => {
+                    errors.push(format!("Couldn't open {}: {e}", path.display()));
+                    Box::new(csv)
+                }
+            }
+        }
+        _ => Box::new(csv),
+    };
+
+    Box::new(Backed {
+        storage,
+        dir,
+        retention,
+    })
+}
+
+// Opens a backup snapshot, or any data directory, without changing it
+pub fn open_snapshot(dir: &Path) -> Result<Box<dyn Storage>, StorageError> {
+    let path = dir.join("hours.db");
+
+    if path.exists() {
+        Ok(Box::new(SqliteStorage::open_read_only(&path)?))
+    } else {
+        Ok(Box::new(CsvStorage::read_only(dir.to_path_buf())))
+    }
+}
+
+// Snapshots the data directory before anything is changed or removed and
+// refuses the change if that fails
+struct Backed {
+    storage: Box<dyn Storage>,
+    dir: PathBuf,
+    retention: Retention,
+}
+
+impl Storage for Backed {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        self.storage.read_entrys(entrys, total_minutes)
+    }
+
+    fn write_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        self.storage.write_entry(entry)
+    }
+
+    fn update_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        backup::snapshot(&self.dir, self.retention).map_err(StorageError::Backup)?;
+        self.storage.update_entry(entry)
+    }
+
+    fn delete_entry(&mut self, id: Uuid) -> Result<(), StorageError> {
+        backup::snapshot(&self.dir, self.retention).map_err(StorageError::Backup)?;
+        self.storage.delete_entry(id)
+    }
+
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        self.storage.read_archive(batches, total_minutes)
+    }
+
+    fn archive_entrys(
+        &mut self,
+        entrys: &[Entry],
+        label: &str,
+        billed_minutes: i64,
+    ) -> Result<(), StorageError> {
+        backup::snapshot(&self.dir, self.retention).map_err(StorageError::Backup)?;
+        self.storage.archive_entrys(entrys, label, billed_minutes)
+    }
+
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError> {
+        self.storage.read_timer()
+    }
+
+    fn write_timer(&mut self, timer: Option<&Timer>) -> Result<(), StorageError> {
+        self.storage.write_timer(timer)
+    }
+
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        self.storage.entrys_between(from, to, entrys)
+    }
+}
+
+// Used while another copy of Hours has the data directory locked
+struct ReadOnly(Box<dyn Storage>);
+
+impl Storage for ReadOnly {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        self.0.read_entrys(entrys, total_minutes)
+    }
+
+    fn write_entry(&mut self, _entry: &Entry) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn update_entry(&mut self, _entry: &Entry) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn delete_entry(&mut self, _id: Uuid) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        self.0.read_archive(batches, total_minutes)
+    }
+
+    fn archive_entrys(
+        &mut self,
+        _entrys: &[Entry],
+        _label: &str,
+        _billed_minutes: i64,
+    ) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError> {
+        self.0.read_timer()
+    }
+
+    fn write_timer(&mut self, _timer: Option<&Timer>) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        self.0.entrys_between(from, to, entrys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn leaves_no_database_when_import_fails() {
+        let dir = std::env::temp_dir().join(format!("hours-test-{}", Uuid::new_v4()));
+        create_dir_all(&dir).unwrap();
+
+        // Two rows with the same id can't both go in the database
+        let id = Uuid::new_v4();
+        write(
+            dir.join("entrys.csv"),
+            format!(
+                "#hours,10\r\n\
+                id,date,description,start,end,end_date,zone,minutes,break_minutes,billed_minutes,batch\r\n\
+                {id},2024-03-04,Planning,,,2024-03-04,UTC,60,0,60,\r\n\
+                {id},2024-03-05,Review,,,2024-03-05,UTC,30,0,30,\r\n"
+            ),
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            let mut errors = Vec::new();
+            let storage = open(dir.clone(), true, false, Retention::default(), &mut errors);
+
+            assert_eq!(errors.len(), 1, "{errors:?}");
+            assert!(!dir.join("hours.db").exists());
+            assert!(!dir.join("hours.db.tmp").exists());
+
+            // Still on the csv files
+            let mut entrys = Vec::new();
+            storage.read_entrys(&mut entrys, &mut 0).unwrap();
+            assert_eq!(entrys.len(), 2);
+        }
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1456 @@
+use std::collections::HashMap;
+use std::fs::{copy, create_dir_all, read, remove_file, File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use uuid::Uuid;
+
+use super::{Storage, StorageError};
+use crate::batch::Batch;
+use crate::csv;
+use crate::entry::Entry;
+use crate::timer::Timer;
+use crate::utils::{local_zone, minutes_between, minutes_in, parse_time, TimeDefaults};
+
+// Stores the current entrys and the archive as csv files in the data directory
+pub struct CsvStorage {
+    dir: PathBuf,
+    // Older files are migrated in memory only and never written back
+    read_only: bool,
+}
+
+impl CsvStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        CsvStorage {
+            dir,
+            read_only: false,
+        }
+    }
+
+    pub fn read_only(dir: PathBuf) -> Self {
+        CsvStorage {
+            dir,
+            read_only: true,
+        }
+    }
+
+    // Whether reading will rewrite any file in the current format
+    pub fn needs_migration(&self) -> Result<bool, StorageError> {
+        for file in ["entrys.csv", "archive.csv"] {
+            if file_version(&self.dir.join(file))?.is_some_and(|version| version < FORMAT_VERSION) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // Path of a file in the data directory, creating the directory if needed
+    pub fn path(&self, file: &str) -> Result<PathBuf, StorageError> {
+        if !self.dir.exists() {
+            create_dir_all(&self.dir)?;
+        }
+
+        Ok(self.dir.join(file))
+    }
+
+    // Every entry in a file along with the batch it was archived in
+    fn read_rows(&self, file: &str) -> Result<Vec<(Entry, Option<u64>)>, StorageError> {
+        let path = self.path(file)?;
+        let mut rows = Vec::new();
+
+        if path.exists() {
+            let (schema, records) = read_records(&path, !self.read_only)?;
+
+            for record in records {
+                if let Some(entry) = get_entry(&schema, &record) {
+                    let batch = schema
+                        .field(&record, Column::Batch as usize)
+                        .and_then(|batch| batch.parse().ok());
+
+                    rows.push((entry, batch));
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
+    fn read_batches(&self) -> Result<Vec<Batch>, StorageError> {
+        let path = self.path("batches.csv")?;
+        let mut batches = Vec::new();
+
+        if path.exists() {
+            let (schema, records) = read_records(&path, !self.read_only)?;
+
+            batches.extend(
+                records
+                    .iter()
+                    .filter_map(|record| get_batch(&schema, record)),
+            );
+        }
+
+        Ok(batches)
+    }
+
+    // Finishes an "Archive all" that was interrupted by a crash or power loss
+    pub fn recover(&self) -> Result<(), StorageError> {
+        let path = self.path(JOURNAL)?;
+
+        if path.exists() {
+            self.replay_journal(&path)?;
+        }
+
+        Ok(())
+    }
+
+    // The journal holds the lengths of the archive and batches files before
+    // the batch was added, then the batch and its entrys. Replaying it cuts
+    // both files back to those lengths before appending so it can be repeated
+    // any number of times, then empties the entrys file and removes the journal.
+    fn replay_journal(&self, journal: &Path) -> Result<(), StorageError> {
+        let archive = self.path("archive.csv")?;
+        let batches = self.path("batches.csv")?;
+        let entrys = self.path("entrys.csv")?;
+
+        let records = csv::parse(&String::from_utf8_lossy(&read(journal)?));
+
+        let lengths = records
+            .first()
+            .filter(|record| record.len() == 3 && record[0] == JOURNAL_MARKER)
+            .and_then(|record| Some((record[1].parse().ok()?, record[2].parse().ok()?)));
+
+        let (archive_length, batches_length) = match (lengths, records.get(1)) {
+            (Some(lengths), Some(_)) => lengths,
+            _ => {
+                return Err(StorageError::Format(format!(
+                    "{} is damaged",
+                    journal.display()
+                )))
+            }
+        };
+
+        let mut file = open_append(&archive, &ENTRY_COLUMNS)?;
+        file.set_len(archive_length)?;
+
+        for record in &records[2..] {
+            csv::write_record(&mut file, record)?;
+        }
+
+        file.sync_all()?;
+
+        let mut file = open_append(&batches, &BATCH_COLUMNS)?;
+        file.set_len(batches_length)?;
+
+        csv::write_record(&mut file, &records[1])?;
+        file.sync_all()?;
+
+        csv::replace_records(&entrys, &header_records(&ENTRY_COLUMNS))?;
+
+        remove_file(journal)?;
+        csv::sync_dir(journal)?;
+
+        Ok(())
+    }
+
+    // Rewrites the entrys file with `change` applied to the record of the entry with the id
+    fn change_entry(
+        &mut self,
+        id: Uuid,
+        change: impl FnOnce(&mut Vec<Vec<String>>, usize),
+    ) -> Result<(), StorageError> {
+        let path = self.path("entrys.csv")?;
+
+        let (schema, mut records) = read_records(&path, true)?;
+        let id = id.to_string();
+
+        let index = records
+            .iter()
+            .position(|record| schema.field(record, Column::Id as usize).as_ref() == Some(&id))
+            .ok_or(StorageError::NotFound)?;
+
+        change(&mut records, index);
+
+        records.splice(0..0, header_records(&ENTRY_COLUMNS));
+        csv::replace_records(&path, &records)?;
+
+        Ok(())
+    }
+}
+
+impl Storage for CsvStorage {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        for (entry, _) in self.read_rows("entrys.csv")? {
+            *total_minutes += entry.minutes;
+            entrys.push(entry);
+        }
+
+        entrys.sort_by(Entry::by_date);
+
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        let mut file = open_append(&self.path("entrys.csv")?, &ENTRY_COLUMNS)?;
+        csv::write_record(&mut file, &entry_record(entry, None))?;
+
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    fn update_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        self.change_entry(entry.id, |records, index| {
+            records[index] = entry_record(entry, None)
+        })
+    }
+
+    fn delete_entry(&mut self, id: Uuid) -> Result<(), StorageError> {
+        self.change_entry(id, |records, index| {
+            records.remove(index);
+        })
+    }
+
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        let start = batches.len();
+        batches.extend(self.read_batches()?);
+
+        let mut index: HashMap<u64, usize> = batches[start..]
+            .iter()
+            .enumerate()
+            .map(|(i, batch)| (batch.id, start + i))
+            .collect();
+
+        let mut unrecorded = Vec::new();
+
+        for (entry, batch) in self.read_rows("archive.csv")? {
+            let id = batch.unwrap_or(0);
+
+            // Entrys archived before batches were recorded
+            let i = *index.entry(id).or_insert_with(|| {
+                batches.push(Batch::new(id, None, String::new(), None, Vec::new()));
+                unrecorded.push(batches.len() - 1);
+                batches.len() - 1
+            });
+
+            *total_minutes += entry.minutes;
+            batches[i].entrys.push(entry);
+        }
+
+        for i in unrecorded {
+            let batch = &mut batches[i];
+            batch.count = batch.entrys.len();
+            batch.minutes = batch.entrys.iter().map(|entry| entry.minutes).sum();
+        }
+
+        batches[start..].sort_by_key(|batch| batch.id);
+
+        Ok(())
+    }
+
+    fn archive_entrys(
+        &mut self,
+        entrys: &[Entry],
+        label: &str,
+        billed_minutes: i64,
+    ) -> Result<(), StorageError> {
+        let archive = self.path("archive.csv")?;
+        let batches = self.path("batches.csv")?;
+        let journal = self.path(JOURNAL)?;
+
+        // Upgrades older files and writes the header to new ones so the
+        // lengths in the journal are where the batch starts
+        let archive_length = open_append(&archive, &ENTRY_COLUMNS)?.metadata()?.len();
+        let batches_length = open_append(&batches, &BATCH_COLUMNS)?.metadata()?.len();
+
+        let mut archived = Vec::new();
+        let mut total_minutes = 0;
+        self.read_archive(&mut archived, &mut total_minutes)?;
+
+        let id = archived.iter().map(|batch| batch.id).max().unwrap_or(0) + 1;
+
+        let batch = Batch::new(
+            id,
+            Some(Local::now().naive_local()),
+            label.to_string(),
+            Some(billed_minutes),
+            entrys.to_vec(),
+        );
+
+        let mut records = vec![
+            vec![
+                JOURNAL_MARKER.to_string(),
+                archive_length.to_string(),
+                batches_length.to_string(),
+            ],
+            batch_record(&batch),
+        ];
+        records.extend(entrys.iter().map(|entry| entry_record(entry, Some(id))));
+
+        // Once the journal is in place the batch is as good as archived
+        csv::replace_records(&journal, &records)?;
+
+        self.replay_journal(&journal)
+    }
+
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError> {
+        let path = self.path(TIMER)?;
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let records = csv::parse(&String::from_utf8_lossy(&read(&path)?));
+
+        match records.first().map(|record| get_timer(record)) {
+            Some(Some(timer)) => Ok(Some(timer)),
+            Some(None) => Err(StorageError::Format(format!(
+                "{} is damaged",
+                path.display()
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    fn write_timer(&mut self, timer: Option<&Timer>) -> Result<(), StorageError> {
+        let path = self.path(TIMER)?;
+
+        match timer {
+            Some(timer) => csv::replace_records(&path, &[timer_record(timer)])?,
+            None if path.exists() => {
+                remove_file(&path)?;
+                csv::sync_dir(&path)?;
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        let mut rows = self.read_rows("entrys.csv")?;
+        rows.extend(self.read_rows("archive.csv")?);
+
+        entrys.extend(
+            rows.into_iter()
+                .map(|(entry, _)| entry)
+                .filter(|entry| entry.date >= from && entry.date <= to),
+        );
+        entrys.sort_by(Entry::by_date);
+
+        Ok(())
+    }
+}
+
+// Holds the running timer as a single record. It is written whole each time
+// and has no version line as nothing reads it but the timer.
+const TIMER: &str = "timer.csv";
+
+// Written while archiving so an interrupted "Archive all" can be finished on the next start
+const JOURNAL: &str = "archive.journal";
+const JOURNAL_MARKER: &str = "#journal";
+
+// The version of the on-disk format. When the columns change bump this and add
+// a step to `migrate` that upgrades files from the previous version.
+const FORMAT_VERSION: u32 = 10;
+
+// First field of the version line that starts every file since version 2
+const FORMAT_MARKER: &str = "#hours";
+
+// The columns of the entrys and archive files in the order they are written.
+// Current entrys have no batch.
+const ENTRY_COLUMNS: [&str; 11] = [
+    "id",
+    "date",
+    "description",
+    "start",
+    "end",
+    "end_date",
+    "zone",
+    "minutes",
+    "break_minutes",
+    "billed_minutes",
+    "batch",
+];
+
+#[derive(Clone, Copy)]
+enum Column {
+    Id,
+    Date,
+    Description,
+    Start,
+    End,
+    EndDate,
+    Zone,
+    Minutes,
+    BreakMinutes,
+    BilledMinutes,
+    Batch,
+}
+
+// The columns of the batches file
+const BATCH_COLUMNS: [&str; 6] = [
+    "id",
+    "archived_at",
+    "label",
+    "count",
+    "minutes",
+    "billed_minutes",
+];
+
+#[derive(Clone, Copy)]
+enum BatchColumn {
+    Id,
+    ArchivedAt,
+    Label,
+    Count,
+    Minutes,
+    BilledMinutes,
+}
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+const ARCHIVED_AT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// Where each column is in the records of a file, taken from its header so
+// columns can be added or reordered without breaking older readers. A file
+// reordered by hand is put back in order when it's next read for writing.
+struct Schema {
+    positions: Vec<Option<usize>>,
+}
+
+impl Schema {
+    fn from_header(header: &[String], columns: &[&str]) -> Self {
+        let positions = columns
+            .iter()
+            .map(|column| header.iter().position(|name| name == column))
+            .collect();
+
+        Schema { positions }
+    }
+
+    fn field(&self, record: &[String], column: usize) -> Option<String> {
+        let position = (*self.positions.get(column)?)?;
+        record.get(position).cloned()
+    }
+}
+
+// Gives every row after the header without a readable id a new one, true if
+// any were given
+fn give_ids(records: &mut [Vec<String>]) -> bool {
+    let Some(position) = records.get(1).and_then(|header| {
+        header
+            .iter()
+            .position(|name| name == ENTRY_COLUMNS[Column::Id as usize])
+    }) else {
+        return false;
+    };
+
+    let mut given = false;
+
+    for record in records.iter_mut().skip(2) {
+        if record
+            .get(position)
+            .is_some_and(|id| Uuid::parse_str(id).is_ok())
+        {
+            continue;
+        }
+
+        if record.len() <= position {
+            record.resize(position + 1, String::new());
+        }
+
+        record[position] = Uuid::new_v4().to_string();
+        given = true;
+    }
+
+    given
+}
+
+fn header_records(columns: &[&str]) -> [Vec<String>; 2] {
+    [
+        vec![FORMAT_MARKER.to_string(), FORMAT_VERSION.to_string()],
+        columns.iter().map(|name| name.to_string()).collect(),
+    ]
+}
+
+// Files from before the version line are version 1
+fn format_version(first: Option<&Vec<String>>) -> Result<u32, StorageError> {
+    match first {
+        Some(record) if record.first().is_some_and(|field| field == FORMAT_MARKER) => record
+            .get(1)
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(|| StorageError::Format("Invalid format version".to_string())),
+        _ => Ok(1),
+    }
+}
+
+// Brings the records of a file up to the current version one step at a time.
+// Each step writes the header of the version it upgrades to so later column
+// changes don't alter it.
+fn migrate(mut records: Vec<Vec<String>>, version: u32, batches: bool) -> Vec<Vec<String>> {
+    // The batches file is from version 3
+    if batches {
+        if version < 5 {
+            records = minutes_column(records, 4);
+        }
+
+        // Version 10 keeps what each batch was billed, which wasn't known
+        // before
+        if version < 10 {
+            records[1].push("billed_minutes".to_string());
+
+            for record in records.iter_mut().skip(2) {
+                record.push(String::new());
+            }
+        }
+
+        records[0] = vec![FORMAT_MARKER.to_string(), FORMAT_VERSION.to_string()];
+        return records;
+    }
+
+    if version < 2 {
+        records = records.into_iter().map(repair_record).collect();
+        records.splice(
+            0..0,
+            [
+                vec![FORMAT_MARKER.to_string(), "2".to_string()],
+                ["date", "description", "start", "end", "hours"]
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect(),
+            ],
+        );
+    }
+
+    if version < 3 {
+        records = number_batches(records);
+    }
+
+    // Every entry gets an id that never changes
+    if version < 4 {
+        records[0] = vec![FORMAT_MARKER.to_string(), "4".to_string()];
+        records[1].insert(0, "id".to_string());
+
+        for record in &mut records[2..] {
+            record.insert(0, Uuid::new_v4().to_string());
+        }
+    }
+
+    if version < 5 {
+        records = typed_times(records);
+    }
+
+    if version < 6 {
+        records = end_dates(records);
+    }
+
+    if version < 7 {
+        records = zones(records);
+    }
+
+    // Version 8 adds unpaid breaks, there were none before
+    if version < 8 {
+        const BREAK_MINUTES: usize = 8;
+
+        records[0] = vec![FORMAT_MARKER.to_string(), "8".to_string()];
+        records[1].insert(BREAK_MINUTES, "break_minutes".to_string());
+
+        for record in records.iter_mut().skip(2) {
+            if record.len() >= BREAK_MINUTES {
+                record.insert(BREAK_MINUTES, "0".to_string());
+            }
+        }
+    }
+
+    // Version 9 keeps the billed minutes, which were the minutes worked
+    if version < 9 {
+        const MINUTES: usize = 7;
+        const BILLED_MINUTES: usize = 9;
+
+        records[0] = vec![FORMAT_MARKER.to_string(), "9".to_string()];
+        records[1].insert(BILLED_MINUTES, "billed_minutes".to_string());
+
+        for record in records.iter_mut().skip(2) {
+            if record.len() >= BILLED_MINUTES {
+                let minutes = record[MINUTES].clone();
+                record.insert(BILLED_MINUTES, minutes);
+            }
+        }
+    }
+
+    // Nothing changed for entrys in version 10
+    records[0] = vec![FORMAT_MARKER.to_string(), FORMAT_VERSION.to_string()];
+
+    records
+}
+
+// Version 5 keeps whole minutes instead of rounded hours
+fn minutes_column(mut records: Vec<Vec<String>>, column: usize) -> Vec<Vec<String>> {
+    if let Some(name) = records.get_mut(1).and_then(|header| header.get_mut(column)) {
+        *name = "minutes".to_string();
+    }
+
+    for record in records.iter_mut().skip(2) {
+        if let Some(field) = record.get_mut(column) {
+            if let Ok(hours) = field.parse::<f64>() {
+                *field = ((hours * 60.0).round() as i64).to_string();
+            }
+        }
+    }
+
+    records
+}
+
+// Version 5 also writes times as they were understood, in 24 hours, rather
+// than as they were typed. The minutes are worked out from the times again as
+// the rounded hours lost some.
+fn typed_times(records: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    // The columns of version 4
+    const START: usize = 3;
+    const END: usize = 4;
+    const HOURS: usize = 5;
+
+    let mut records = minutes_column(records, HOURS);
+    records[0] = vec![FORMAT_MARKER.to_string(), "5".to_string()];
+
+    for record in records.iter_mut().skip(2) {
+        let time = |i: usize| {
+            record
+                .get(i)
+                .and_then(|time| parse_time(time, TimeDefaults::default()).ok())
+        };
+
+        let (start, end) = (time(START), time(END));
+
+        if let (Some(start), Some(end)) = (start, end) {
+            record[HOURS] = minutes_between(start, end).to_string();
+        }
+
+        for (i, time) in [(START, start), (END, end)] {
+            if let (Some(field), Some(time)) = (record.get_mut(i), time) {
+                *field = time.format(TIME_FORMAT).to_string();
+            }
+        }
+    }
+
+    records
+}
+
+// Version 6 writes the date each entry ends on. Before then an end before the
+// start always meant the next day.
+fn end_dates(mut records: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    // The columns of version 5
+    const DATE: usize = 1;
+    const START: usize = 3;
+    const END: usize = 4;
+
+    records[0] = vec![FORMAT_MARKER.to_string(), "6".to_string()];
+    records[1].insert(END + 1, "end_date".to_string());
+
+    for record in records.iter_mut().skip(2) {
+        let time = |i: usize| {
+            record
+                .get(i)
+                .and_then(|time| NaiveTime::parse_from_str(time, TIME_FORMAT).ok())
+        };
+
+        let overnight = matches!((time(START), time(END)), (Some(start), Some(end)) if end < start);
+
+        let end_date = record
+            .get(DATE)
+            .and_then(|date| NaiveDate::parse_from_str(date, DATE_FORMAT).ok())
+            .map(|date| {
+                let date = if overnight { date + Days::new(1) } else { date };
+                date.format(DATE_FORMAT).to_string()
+            })
+            .unwrap_or_default();
+
+        if record.len() > END {
+            record.insert(END + 1, end_date);
+        }
+    }
+
+    records
+}
+
+// Version 7 writes the zone of each entry. Older ones are taken to be in the
+// current zone, and their minutes are counted again in case they went over a
+// change to or from daylight saving time.
+fn zones(mut records: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    // The columns of version 6
+    const DATE: usize = 1;
+    const START: usize = 3;
+    const END: usize = 4;
+    const END_DATE: usize = 5;
+    const MINUTES: usize = 6;
+
+    let zone = local_zone();
+
+    records[0] = vec![FORMAT_MARKER.to_string(), "7".to_string()];
+    records[1].insert(END_DATE + 1, "zone".to_string());
+
+    for record in records.iter_mut().skip(2) {
+        if record.len() <= END_DATE {
+            continue;
+        }
+
+        let at = |date: usize, time: usize| {
+            let date = NaiveDate::parse_from_str(record.get(date)?, DATE_FORMAT).ok()?;
+            let time = NaiveTime::parse_from_str(record.get(time)?, TIME_FORMAT).ok()?;
+            Some(date.and_time(time))
+        };
+
+        let minutes = at(DATE, START)
+            .zip(at(END_DATE, END))
+            .and_then(|(from, to)| minutes_in(zone, from, to));
+
+        record.insert(END_DATE + 1, zone.name().to_string());
+
+        if let (Some(field), Some(minutes)) = (record.get_mut(MINUTES + 1), minutes) {
+            *field = minutes.to_string();
+        }
+    }
+
+    records
+}
+
+// Version 2 ended each archived batch with a "-" line, version 3 gives every
+// entry the number of its batch instead. Entrys after the last "-", which is
+// all of them in the entrys file, aren't archived.
+fn number_batches(records: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let is_separator = |record: &Vec<String>| record.len() == 1 && record[0] == "-";
+
+    let last_separator = records.iter().rposition(is_separator).unwrap_or(0);
+    let mut batch = 1;
+
+    let mut migrated = Vec::with_capacity(records.len());
+
+    for (i, mut record) in records.into_iter().enumerate() {
+        match i {
+            0 => migrated.push(vec![FORMAT_MARKER.to_string(), "3".to_string()]),
+            1 => {
+                record.push("batch".to_string());
+                migrated.push(record);
+            }
+            _ if is_separator(&record) => batch += 1,
+            _ => {
+                record.push(if i < last_separator {
+                    batch.to_string()
+                } else {
+                    String::new()
+                });
+                migrated.push(record);
+            }
+        }
+    }
+
+    migrated
+}
+
+// Reads every record of a csv file after its version line and header,
+// migrating an older file to the current format and writing it back if `write`.
+fn read_records(path: &Path, write: bool) -> Result<(Schema, Vec<Vec<String>>), StorageError> {
+    let bytes = read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    let version = format_version(csv::parse(text.lines().next().unwrap_or("")).first())?;
+
+    // Before version 2 a quote was just part of the text
+    let mut records = if version < 2 {
+        csv::parse_unquoted(&text)
+    } else {
+        csv::parse(&text)
+    };
+
+    if records.is_empty() {
+        return Ok((Schema::from_header(&[], &[]), records));
+    }
+
+    if version > FORMAT_VERSION {
+        return Err(StorageError::Format(format!(
+            "{} was written by a newer version",
+            path.display()
+        )));
+    }
+
+    let batches = path.ends_with("batches.csv");
+
+    if version < FORMAT_VERSION {
+        records = migrate(records, version, batches);
+
+        if write {
+            // Kept for good next to the file rather than with the snapshots,
+            // which are pruned, in case the migration gets something wrong
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".v{version}.bak"));
+
+            if !Path::new(&backup).exists() {
+                copy(path, &backup)?;
+            }
+
+            csv::replace_records(path, &records)?;
+        }
+    }
+
+    // Rows added by hand without an id are given one for good, otherwise
+    // they would get a new one each time and couldn't be changed
+    if write && !batches && give_ids(&mut records) {
+        csv::replace_records(path, &records)?;
+    }
+
+    let mut rows = records.split_off(2.min(records.len()));
+
+    let columns: &[&str] = if batches {
+        &BATCH_COLUMNS
+    } else {
+        &ENTRY_COLUMNS
+    };
+
+    let header = records.get(1).map_or(&[][..], Vec::as_slice);
+    let schema = Schema::from_header(header, columns);
+
+    // Columns moved by hand are put back in order, as rows are added and
+    // changed in that order. Any Hours doesn't know are left out.
+    if write && records.len() == 2 && !in_order(header, columns) {
+        for row in &mut rows {
+            *row = (0..columns.len())
+                .map(|column| schema.field(row, column).unwrap_or_default())
+                .collect();
+        }
+
+        let header = header_records(columns);
+        let schema = Schema::from_header(&header[1], columns);
+
+        let mut records = header.to_vec();
+        records.extend_from_slice(&rows);
+        csv::replace_records(path, &records)?;
+
+        return Ok((schema, rows));
+    }
+
+    Ok((schema, rows))
+}
+
+// True if the header is just the columns in the order they're written in
+fn in_order(header: &[String], columns: &[&str]) -> bool {
+    header
+        .iter()
+        .map(String::as_str)
+        .eq(columns.iter().copied())
+}
+
+// Whether rows can be appended to a file as it is, which needs the current
+// version and the columns in order
+fn appendable(path: &Path, columns: &[&str]) -> Result<bool, StorageError> {
+    if !path.exists() {
+        return Ok(true);
+    }
+
+    let mut lines = String::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    reader.read_line(&mut lines)?;
+    reader.read_line(&mut lines)?;
+
+    let records = csv::parse(&lines);
+
+    if records.is_empty() {
+        return Ok(true);
+    }
+
+    Ok(format_version(records.first())? == FORMAT_VERSION
+        && records
+            .get(1)
+            .is_some_and(|header| in_order(header, columns)))
+}
+
+// Version of a csv file from its first line, None if it is missing or empty
+fn file_version(path: &Path) -> Result<Option<u32>, StorageError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut first_line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut first_line)?;
+
+    let first = csv::parse(&first_line);
+
+    if first.is_empty() {
+        return Ok(None);
+    }
+
+    format_version(first.first()).map(Some)
+}
+
+// Opens a csv file for appending, upgrading an older file or putting its
+// columns in order first and writing the version line and header to a new or
+// emptied one.
+fn open_append(path: &Path, columns: &[&str]) -> Result<File, StorageError> {
+    if !appendable(path, columns)? {
+        read_records(path, true)?;
+    }
+
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+
+    if file.metadata()?.len() == 0 {
+        for record in header_records(columns) {
+            csv::write_record(&mut file, &record)?;
+        }
+    }
+
+    Ok(file)
+}
+
+fn entry_record(entry: &Entry, batch: Option<u64>) -> Vec<String> {
+    let time = |time: Option<NaiveTime>| {
+        time.map(|time| time.format(TIME_FORMAT).to_string())
+            .unwrap_or_default()
+    };
+
+    vec![
+        entry.id.to_string(),
+        entry.date.format(DATE_FORMAT).to_string(),
+        entry.description.clone(),
+        time(entry.start),
+        time(entry.end),
+        entry.end_date.format(DATE_FORMAT).to_string(),
+        entry.zone.name().to_string(),
+        entry.minutes.to_string(),
+        entry.break_minutes.to_string(),
+        entry.billed_minutes.to_string(),
+        batch.map(|batch| batch.to_string()).unwrap_or_default(),
+    ]
+}
+
+fn get_entry(schema: &Schema, record: &[String]) -> Option<Entry> {
+    // Only files opened read only still have rows without an id, they show up
+    // but can't be changed anyway
+    let id = schema
+        .field(record, Column::Id as usize)
+        .and_then(|id| Uuid::parse_str(&id).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    // An empty or unreadable time is left out, the minutes still count
+    let time = |column: Column| {
+        schema
+            .field(record, column as usize)
+            .and_then(|time| NaiveTime::parse_from_str(&time, TIME_FORMAT).ok())
+    };
+
+    let date = schema.field(record, Column::Date as usize)?;
+    let date = NaiveDate::parse_from_str(&date, DATE_FORMAT).ok()?;
+
+    // Without an end date it ends on the day it starts
+    let end_date = schema
+        .field(record, Column::EndDate as usize)
+        .and_then(|date| NaiveDate::parse_from_str(&date, DATE_FORMAT).ok())
+        .unwrap_or(date);
+
+    // Without a zone it was written in the current one
+    let zone = schema
+        .field(record, Column::Zone as usize)
+        .and_then(|zone| zone.parse().ok())
+        .unwrap_or_else(local_zone);
+
+    let minutes = schema
+        .field(record, Column::Minutes as usize)?
+        .parse()
+        .ok()?;
+
+    Some(Entry {
+        id,
+        date,
+        description: schema.field(record, Column::Description as usize)?,
+        start: time(Column::Start),
+        end: time(Column::End),
+        end_date,
+        zone,
+        minutes,
+        break_minutes: schema
+            .field(record, Column::BreakMinutes as usize)
+            .and_then(|minutes| minutes.parse().ok())
+            .unwrap_or(0),
+        billed_minutes: schema
+            .field(record, Column::BilledMinutes as usize)
+            .and_then(|minutes| minutes.parse().ok())
+            .unwrap_or(minutes),
+    })
+}
+
+fn timer_record(timer: &Timer) -> Vec<String> {
+    vec![
+        timer.id.to_string(),
+        timer.started.to_rfc3339(),
+        timer.zone.name().to_string(),
+        timer.description.clone(),
+    ]
+}
+
+fn get_timer(record: &[String]) -> Option<Timer> {
+    match record {
+        [id, started, zone, description] => Some(Timer {
+            id: Uuid::parse_str(id).ok()?,
+            started: DateTime::parse_from_rfc3339(started)
+                .ok()?
+                .with_timezone(&Utc),
+            zone: zone.parse().ok()?,
+            description: description.clone(),
+        }),
+        _ => None,
+    }
+}
+
+fn batch_record(batch: &Batch) -> Vec<String> {
+    vec![
+        batch.id.to_string(),
+        batch
+            .archived_at
+            .map(|at| at.format(ARCHIVED_AT_FORMAT).to_string())
+            .unwrap_or_default(),
+        batch.label.clone(),
+        batch.count.to_string(),
+        batch.minutes.to_string(),
+        batch
+            .billed_minutes
+            .map(|minutes| minutes.to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+fn get_batch(schema: &Schema, record: &[String]) -> Option<Batch> {
+    let archived_at = schema.field(record, BatchColumn::ArchivedAt as usize)?;
+
+    Some(Batch {
+        id: schema
+            .field(record, BatchColumn::Id as usize)?
+            .parse()
+            .ok()?,
+        archived_at: NaiveDateTime::parse_from_str(&archived_at, ARCHIVED_AT_FORMAT).ok(),
+        label: schema.field(record, BatchColumn::Label as usize)?,
+        count: schema
+            .field(record, BatchColumn::Count as usize)?
+            .parse()
+            .ok()?,
+        minutes: schema
+            .field(record, BatchColumn::Minutes as usize)?
+            .parse()
+            .ok()?,
+        billed_minutes: schema
+            .field(record, BatchColumn::BilledMinutes as usize)
+            .and_then(|minutes| minutes.parse().ok()),
+        entrys: Vec::new(),
+    })
+}
+
+// Descriptions containing commas used to be written bare, which splits them
+// over several fields. The start, end and hours are always the last three so
+// everything between the date and those is the description.
+fn repair_record(mut record: Vec<String>) -> Vec<String> {
+    if record.len() > 5 {
+        let tail = record.split_off(record.len() - 3);
+        let description = record.split_off(1).join(",");
+
+        record.push(description);
+        record.extend(tail);
+    }
+
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, remove_dir_all, write};
+
+    use chrono_tz::Tz;
+
+    use super::*;
+
+    // An empty data directory of its own for each test
+    fn data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hours-test-{}", Uuid::new_v4()));
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let dir = data_dir();
+
+        write(
+            dir.join("entrys.csv"),
+            "2024-01-01,Fix login, then deploy,9:00am,5:00pm,8\n\
+            2024-01-02,Night shift,10pm,2am,4\n\
+            2024-01-03,\"Hotfix,9am,10:30am,1.5\n",
+        )
+        .unwrap();
+
+        write(
+            dir.join("archive.csv"),
+            "2023-12-01,Old work,9am,11am,2\n\
+            -\n\
+            2023-12-02,More, with a comma,1pm,1:20pm,0.33\n\
+            2023-12-03,Same batch,1pm,2pm,1\n\
+            -\n",
+        )
+        .unwrap();
+
+        let storage = CsvStorage::new(dir.clone());
+
+        let mut entrys = Vec::new();
+        let mut total_minutes = 0;
+        storage
+            .read_entrys(&mut entrys, &mut total_minutes)
+            .unwrap();
+        entrys.sort_by_key(|entry| entry.date);
+
+        let read: Vec<_> = entrys
+            .iter()
+            .map(|entry| {
+                (
+                    entry.description.as_str(),
+                    entry.minutes,
+                    entry.billed_minutes,
+                    entry.end_date.to_string(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            read,
+            [
+                ("Fix login, then deploy", 480, 480, "2024-01-01".to_string()),
+                ("Night shift", 240, 240, "2024-01-03".to_string()),
+                ("\"Hotfix", 90, 90, "2024-01-03".to_string()),
+            ]
+        );
+        assert_eq!(total_minutes, 810);
+        assert_eq!(entrys[1].start, NaiveTime::from_hms_opt(22, 0, 0));
+
+        let mut batches = Vec::new();
+        let mut archived_minutes = 0;
+        storage
+            .read_archive(&mut batches, &mut archived_minutes)
+            .unwrap();
+
+        let read: Vec<_> = batches
+            .iter()
+            .map(|batch| {
+                let descriptions: Vec<_> = batch
+                    .entrys
+                    .iter()
+                    .map(|entry| entry.description.as_str())
+                    .collect();
+
+                (batch.id, batch.count, batch.minutes, descriptions)
+            })
+            .collect();
+
+        assert_eq!(
+            read,
+            [
+                (1, 1, 120, vec!["Old work"]),
+                (2, 2, 80, vec!["More, with a comma", "Same batch"]),
+            ]
+        );
+        assert_eq!(archived_minutes, 200);
+
+        // Both files were written back in the current format with the ids
+        // they were given, and the originals kept
+        for file in ["entrys.csv", "archive.csv"] {
+            let text = read_to_string(dir.join(file)).unwrap();
+            assert!(text.starts_with(&format!("{FORMAT_MARKER},{FORMAT_VERSION}\r\n")));
+            assert!(dir.join(format!("{file}.v1.bak")).exists());
+        }
+
+        let mut again = Vec::new();
+        storage.read_entrys(&mut again, &mut 0).unwrap();
+        again.sort_by_key(|entry| entry.date);
+
+        let ids = |entrys: &[Entry]| entrys.iter().map(|entry| entry.id).collect::<Vec<_>>();
+        assert_eq!(ids(&again), ids(&entrys));
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    fn entry(day: u32, description: &str) -> Entry {
+        let date = NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        Entry::new(date, description.to_string(), None, None, date, Tz::UTC, 60)
+    }
+
+    fn data_files(dir: &Path) -> Vec<Vec<u8>> {
+        ["entrys.csv", "archive.csv", "batches.csv"]
+            .iter()
+            .map(|file| read(dir.join(file)).unwrap_or_default())
+            .collect()
+    }
+
+    fn restore(dir: &Path, files: &[Vec<u8>]) {
+        for (file, bytes) in ["entrys.csv", "archive.csv", "batches.csv"]
+            .iter()
+            .zip(files)
+        {
+            write(dir.join(file), bytes).unwrap();
+        }
+    }
+
+    #[test]
+    fn replays_journal() {
+        let dir = data_dir();
+        let mut storage = CsvStorage::new(dir.clone());
+
+        storage.write_entry(&entry(1, "First batch")).unwrap();
+        storage
+            .archive_entrys(&[entry(1, "First batch")], "", 60)
+            .unwrap();
+
+        let entrys = [entry(2, "Planning"), entry(3, "Review")];
+        for entry in &entrys {
+            storage.write_entry(entry).unwrap();
+        }
+
+        let before = data_files(&dir);
+        storage.archive_entrys(&entrys, "Second", 120).unwrap();
+        let after = data_files(&dir);
+
+        // The journal that archiving wrote, made again from what it added
+        let mut journal = vec![vec![
+            JOURNAL_MARKER.to_string(),
+            before[1].len().to_string(),
+            before[2].len().to_string(),
+        ]];
+        journal.extend(csv::parse(&String::from_utf8_lossy(
+            &after[2][before[2].len()..],
+        )));
+        journal.extend(csv::parse(&String::from_utf8_lossy(
+            &after[1][before[1].len()..],
+        )));
+        assert_eq!(journal.len(), 4);
+
+        // As though it stopped part way through appending to the archive
+        restore(&dir, &before);
+        let mut archive = open_append(&dir.join("archive.csv"), &ENTRY_COLUMNS).unwrap();
+        csv::write_record(&mut archive, &journal[2]).unwrap();
+
+        let path = dir.join(JOURNAL);
+
+        // Then again as though it stopped before the journal was removed
+        for _ in 0..2 {
+            csv::replace_records(&path, &journal).unwrap();
+            storage.recover().unwrap();
+
+            assert!(!path.exists());
+            assert_eq!(data_files(&dir), after);
+        }
+
+        let mut batches = Vec::new();
+        storage.read_archive(&mut batches, &mut 0).unwrap();
+
+        let read: Vec<_> = batches
+            .iter()
+            .map(|batch| {
+                (
+                    batch.id,
+                    batch.label.as_str(),
+                    batch.count,
+                    batch.billed_minutes,
+                )
+            })
+            .collect();
+        assert_eq!(read, [(1, "", 1, Some(60)), (2, "Second", 2, Some(120))]);
+
+        let mut entrys = Vec::new();
+        storage.read_entrys(&mut entrys, &mut 0).unwrap();
+        assert!(entrys.is_empty());
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_damaged_journal() {
+        let dir = data_dir();
+        let mut storage = CsvStorage::new(dir.clone());
+
+        storage.write_entry(&entry(1, "Planning")).unwrap();
+        storage
+            .archive_entrys(&[entry(1, "Planning")], "", 60)
+            .unwrap();
+        storage.write_entry(&entry(2, "Review")).unwrap();
+
+        let before = data_files(&dir);
+        let path = dir.join(JOURNAL);
+
+        let journals = [
+            "",
+            "#journal,12\r\n",
+            "#journal,12,x\r\n",
+            "not a journal,12,12\r\n",
+            // Lengths but no batch
+            "#journal,0,0\r\n",
+        ];
+
+        for journal in journals {
+            write(&path, journal).unwrap();
+
+            let result = storage.recover();
+
+            assert!(
+                matches!(result, Err(StorageError::Format(_))),
+                "{journal:?}"
+            );
+            assert_eq!(data_files(&dir), before, "{journal:?}");
+        }
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn gives_ids_once() {
+        let dir = data_dir();
+        let path = dir.join("entrys.csv");
+
+        let mut text = header_records(&ENTRY_COLUMNS)
+            .iter()
+            .map(|record| record.join(","))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        text.push_str("\r\n,2024-03-04,Added by hand,09:00,10:00,2024-03-04,UTC,60,0,60,\r\n");
+
+        write(&path, &text).unwrap();
+
+        // Left as it is when read only
+        let mut entrys = Vec::new();
+        CsvStorage::read_only(dir.clone())
+            .read_entrys(&mut entrys, &mut 0)
+            .unwrap();
+        assert_eq!(entrys.len(), 1);
+        assert_eq!(read_to_string(&path).unwrap(), text);
+
+        let mut storage = CsvStorage::new(dir.clone());
+
+        let mut entrys = Vec::new();
+        storage.read_entrys(&mut entrys, &mut 0).unwrap();
+        let mut again = Vec::new();
+        storage.read_entrys(&mut again, &mut 0).unwrap();
+        assert_eq!(entrys[0].id, again[0].id);
+
+        let edited = Entry {
+            description: "Edited".to_string(),
+            ..entrys[0].clone()
+        };
+        storage.update_entry(&edited).unwrap();
+
+        let mut entrys = Vec::new();
+        storage.read_entrys(&mut entrys, &mut 0).unwrap();
+        assert_eq!(entrys[0].description, "Edited");
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_reordered_columns_in_order() {
+        let dir = data_dir();
+        let path = dir.join("entrys.csv");
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        write(
+            &path,
+            format!(
+                "#hours,10\r\n\
+                minutes,description,notes,date,id\r\n\
+                60,Planning,By hand,2024-03-04,{first}\r\n\
+                30,Review,,2024-03-05,{second}\r\n"
+            ),
+        )
+        .unwrap();
+
+        let mut storage = CsvStorage::new(dir.clone());
+
+        // Appended before anything is read
+        let date = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap();
+        let added = Entry::new(date, "Added".to_string(), None, None, date, Tz::UTC, 45);
+        storage.write_entry(&added).unwrap();
+
+        let mut entrys = Vec::new();
+        storage.read_entrys(&mut entrys, &mut 0).unwrap();
+        let edited = entrys.iter().find(|entry| entry.id == first).unwrap();
+        storage
+            .update_entry(&Entry {
+                description: "Edited".to_string(),
+                ..edited.clone()
+            })
+            .unwrap();
+
+        let mut entrys = Vec::new();
+        let mut minutes = 0;
+        storage.read_entrys(&mut entrys, &mut minutes).unwrap();
+        entrys.sort_by_key(|entry| entry.date);
+
+        let read: Vec<_> = entrys
+            .iter()
+            .map(|entry| (entry.description.as_str(), entry.minutes))
+            .collect();
+        assert_eq!(read, [("Edited", 60), ("Review", 30), ("Added", 45)]);
+        assert_eq!(minutes, 135);
+
+        let text = read_to_string(&path).unwrap();
+        assert!(text.contains(",billed_minutes,batch\r\n"));
+        assert!(!text.contains("By hand"));
+        assert!(text.contains(&format!("{second},2024-03-05,Review,")));
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_batches_without_billing() {
+        let dir = data_dir();
+
+        write(
+            dir.join("batches.csv"),
+            "#hours,9\r\nid,archived_at,label,count,minutes\r\n1,2024-03-04 17:00:00,March,1,60\r\n",
+        )
+        .unwrap();
+
+        let storage = CsvStorage::new(dir.clone());
+        let batches = storage.read_batches().unwrap();
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].minutes, 60);
+        assert_eq!(batches[0].billed_minutes, None);
+
+        let text = read_to_string(dir.join("batches.csv")).unwrap();
+        assert!(text.contains("minutes,billed_minutes\r\n"));
+        assert!(text.ends_with(",March,1,60,\r\n"));
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_newer_files() {
+        let dir = data_dir();
+        let path = dir.join("entrys.csv");
+        let text = format!("{FORMAT_MARKER},{}\r\nid,date\r\n", FORMAT_VERSION + 1);
+
+        write(&path, &text).unwrap();
+
+        let result = CsvStorage::new(dir.clone()).read_entrys(&mut Vec::new(), &mut 0);
+
+        assert!(matches!(result, Err(StorageError::Format(_))));
+        assert_eq!(read_to_string(&path).unwrap(), text);
+
+        remove_dir_all(dir).unwrap();
+    }
+}
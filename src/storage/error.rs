@@ -0,0 +1,55 @@
+use std::{fmt, io};
+
+// What went wrong reading or changing the entrys
+#[derive(Debug)]
+pub enum StorageError {
+    // A file couldn't be read or written, like when the disk is full or
+    // permission is denied
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    // The entry to change isn't one of the current entrys
+    NotFound,
+    // Another copy of Hours is using the data directory
+    ReadOnly,
+    // A file or database that isn't in a format this version can read
+    Format(String),
+    // The data couldn't be backed up first so nothing was changed
+    Backup(io::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "{e}"),
+            StorageError::Sqlite(e) => write!(f, "Database error: {e}"),
+            StorageError::NotFound => f.write_str("The entry no longer exists"),
+            StorageError::ReadOnly => {
+                f.write_str("Another copy of Hours is using the data directory")
+            }
+            StorageError::Format(message) => f.write_str(message),
+            StorageError::Backup(e) => write!(f, "Couldn't back up first: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(e) | StorageError::Backup(e) => Some(e),
+            StorageError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{Local, NaiveDate};
+use uuid::Uuid;
+
+use super::{Storage, StorageError};
+use crate::batch::Batch;
+use crate::entry::Entry;
+use crate::timer::Timer;
+
+// Keeps everything in memory for demo sessions and tests, nothing is written to disk
+#[derive(Default)]
+pub struct MemoryStorage {
+    entrys: Vec<Entry>,
+    archive: Vec<Batch>,
+    timer: Option<Timer>,
+}
+
+impl MemoryStorage {
+    fn position(&self, id: Uuid) -> Result<usize, StorageError> {
+        self.entrys
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or(StorageError::NotFound)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        for entry in &self.entrys {
+            *total_minutes += entry.minutes;
+            entrys.push(entry.clone());
+        }
+
+        entrys.sort_by(Entry::by_date);
+
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        self.entrys.push(entry.clone());
+
+        Ok(())
+    }
+
+    fn update_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        let index = self.position(entry.id)?;
+        self.entrys[index] = entry.clone();
+
+        Ok(())
+    }
+
+    fn delete_entry(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let index = self.position(id)?;
+        self.entrys.remove(index);
+
+        Ok(())
+    }
+
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        for batch in &self.archive {
+            *total_minutes += batch.minutes;
+            batches.push(batch.clone());
+        }
+
+        Ok(())
+    }
+
+    fn archive_entrys(
+        &mut self,
+        entrys: &[Entry],
+        label: &str,
+        billed_minutes: i64,
+    ) -> Result<(), StorageError> {
+        let id = self.archive.len() as u64 + 1;
+
+        self.archive.push(Batch::new(
+            id,
+            Some(Local::now().naive_local()),
+            label.to_string(),
+            Some(billed_minutes),
+            entrys.to_vec(),
+        ));
+        self.entrys.clear();
+
+        Ok(())
+    }
+
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError> {
+        Ok(self.timer.clone())
+    }
+
+    fn write_timer(&mut self, timer: Option<&Timer>) -> Result<(), StorageError> {
+        self.timer = timer.cloned();
+
+        Ok(())
+    }
+
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        entrys.extend(
+            self.entrys
+                .iter()
+                .chain(self.archive.iter().flat_map(|batch| &batch.entrys))
+                .filter(|entry| entry.date >= from && entry.date <= to)
+                .cloned(),
+        );
+        entrys.sort_by(Entry::by_date);
+
+        Ok(())
+    }
+}
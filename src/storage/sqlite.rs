@@ -0,0 +1,504 @@
+use std::fs::{remove_file, rename};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use uuid::Uuid;
+
+use super::{Storage, StorageError};
+use crate::batch::Batch;
+use crate::entry::Entry;
+use crate::timer::Timer;
+
+// Bump this and add a step to `migrate` when the tables change
+const SCHEMA_VERSION: i32 = 1;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+const ARCHIVED_AT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const COLUMNS: &str =
+    "uuid, date, description, start, end, end_date, zone, minutes, break_minutes, billed_minutes";
+
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+fn conversion_error(
+    column: usize,
+    e: impl std::error::Error + Send + Sync + 'static,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e))
+}
+
+// Times are kept as text, empty when there isn't one
+fn time_text(time: Option<NaiveTime>) -> String {
+    time.map(|time| time.format(TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+fn get_entry(row: &Row) -> rusqlite::Result<Entry> {
+    let id: String = row.get(0)?;
+    let zone: String = row.get(6)?;
+
+    let date = |column: usize| -> rusqlite::Result<NaiveDate> {
+        let date: String = row.get(column)?;
+        NaiveDate::parse_from_str(&date, DATE_FORMAT).map_err(|e| conversion_error(column, e))
+    };
+
+    let time = |column: usize| -> rusqlite::Result<Option<NaiveTime>> {
+        let time: String = row.get(column)?;
+
+        if time.is_empty() {
+            return Ok(None);
+        }
+
+        NaiveTime::parse_from_str(&time, TIME_FORMAT)
+            .map(Some)
+            .map_err(|e| conversion_error(column, e))
+    };
+
+    Ok(Entry {
+        id: Uuid::parse_str(&id).map_err(|e| conversion_error(0, e))?,
+        date: date(1)?,
+        description: row.get(2)?,
+        start: time(3)?,
+        end: time(4)?,
+        end_date: date(5)?,
+        zone: zone.parse().map_err(|e| conversion_error(6, e))?,
+        minutes: row.get(7)?,
+        break_minutes: row.get(8)?,
+        billed_minutes: row.get(9)?,
+    })
+}
+
+fn get_timer(row: &Row) -> rusqlite::Result<Timer> {
+    let id: String = row.get(0)?;
+    let started: String = row.get(1)?;
+    let zone: String = row.get(2)?;
+
+    Ok(Timer {
+        id: Uuid::parse_str(&id).map_err(|e| conversion_error(0, e))?,
+        started: DateTime::parse_from_rfc3339(&started)
+            .map_err(|e| conversion_error(1, e))?
+            .with_timezone(&Utc),
+        zone: zone.parse().map_err(|e| conversion_error(2, e))?,
+        description: row.get(3)?,
+    })
+}
+
+fn get_batch(row: &Row) -> rusqlite::Result<Batch> {
+    let archived_at: Option<String> = row.get(1)?;
+
+    Ok(Batch {
+        id: row.get(0)?,
+        archived_at: archived_at
+            .and_then(|at| NaiveDateTime::parse_from_str(&at, ARCHIVED_AT_FORMAT).ok()),
+        label: row.get(2)?,
+        count: row.get(3)?,
+        minutes: row.get(4)?,
+        billed_minutes: row.get(5)?,
+        entrys: Vec::new(),
+    })
+}
+
+fn insert_entry(conn: &Connection, entry: &Entry, batch: Option<u64>) -> rusqlite::Result<()> {
+    conn.prepare_cached(&format!(
+        "INSERT INTO entrys ({COLUMNS}, batch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
+    ))?
+    .execute(params![
+        entry.id.to_string(),
+        entry.date.format(DATE_FORMAT).to_string(),
+        entry.description,
+        time_text(entry.start),
+        time_text(entry.end),
+        entry.end_date.format(DATE_FORMAT).to_string(),
+        entry.zone.name(),
+        entry.minutes,
+        entry.break_minutes,
+        entry.billed_minutes,
+        batch
+    ])?;
+
+    Ok(())
+}
+
+// Adds the batch along with its entrys
+fn insert_batch(conn: &Connection, batch: &Batch) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO batches (id, archived_at, label, count, minutes, billed_minutes)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            batch.id,
+            batch
+                .archived_at
+                .map(|at| at.format(ARCHIVED_AT_FORMAT).to_string()),
+            batch.label,
+            batch.count,
+            batch.minutes,
+            batch.billed_minutes
+        ],
+    )?;
+
+    for entry in &batch.entrys {
+        insert_entry(conn, entry, Some(batch.id))?;
+    }
+
+    Ok(())
+}
+
+fn insert_timer(conn: &Connection, timer: &Timer) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO timer (id, uuid, started, zone, description) VALUES (1, ?1, ?2, ?3, ?4)",
+        params![
+            timer.id.to_string(),
+            timer.started.to_rfc3339(),
+            timer.zone.name(),
+            timer.description
+        ],
+    )?;
+
+    Ok(())
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        let conn = Connection::open(path).map_err(StorageError::Sqlite)?;
+        let mut storage = SqliteStorage { conn };
+
+        storage.migrate()?;
+
+        Ok(storage)
+    }
+
+    // Makes a new database with everything from `from`. It is built at a
+    // temporary path and only renamed into place once the import worked, so
+    // a failed one leaves no empty database to be opened next time.
+    pub fn create(path: &Path, from: &dyn Storage) -> Result<Self, StorageError> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        if tmp.exists() {
+            remove_file(&tmp)?;
+        }
+
+        // Closed again before the rename
+        let imported = SqliteStorage::open(&tmp).and_then(|mut storage| storage.import(from));
+
+        if let Err(e) = imported {
+            let _ = remove_file(&tmp);
+            return Err(e);
+        }
+
+        rename(&tmp, path)?;
+
+        SqliteStorage::open(path)
+    }
+
+    // Opens a database without creating or migrating it
+    pub fn open_read_only(path: &Path) -> Result<Self, StorageError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(StorageError::Sqlite)?;
+
+        let version: i32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(StorageError::Sqlite)?;
+
+        if version != SCHEMA_VERSION {
+            return Err(StorageError::Format(format!(
+                "{} needs migrating",
+                path.display()
+            )));
+        }
+
+        Ok(SqliteStorage { conn })
+    }
+
+    fn migrate(&mut self) -> Result<(), StorageError> {
+        let tx = self.conn.transaction().map_err(StorageError::Sqlite)?;
+
+        let version: i32 = tx
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(StorageError::Sqlite)?;
+
+        if version > SCHEMA_VERSION {
+            return Err(StorageError::Format(
+                "The database was written by a newer version".to_string(),
+            ));
+        }
+
+        // Current entrys have no batch, archived ones share a batch number
+        // per "Archive all". The timer only ever has one row.
+        if version < 1 {
+            tx.execute_batch(
+                "CREATE TABLE entrys (
+                    id INTEGER PRIMARY KEY,
+                    uuid TEXT NOT NULL,
+                    date TEXT NOT NULL,
+                    description TEXT NOT NULL,
+                    start TEXT NOT NULL,
+                    end TEXT NOT NULL,
+                    end_date TEXT NOT NULL,
+                    zone TEXT NOT NULL,
+                    minutes INTEGER NOT NULL,
+                    break_minutes INTEGER NOT NULL,
+                    billed_minutes INTEGER NOT NULL,
+                    batch INTEGER
+                );
+                CREATE UNIQUE INDEX entrys_uuid ON entrys (uuid);
+                CREATE INDEX entrys_date ON entrys (date);
+                CREATE INDEX entrys_batch ON entrys (batch);
+                CREATE TABLE batches (
+                    id INTEGER PRIMARY KEY,
+                    archived_at TEXT,
+                    label TEXT NOT NULL DEFAULT '',
+                    count INTEGER NOT NULL,
+                    minutes INTEGER NOT NULL,
+                    billed_minutes INTEGER
+                );
+                CREATE TABLE timer (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    uuid TEXT NOT NULL,
+                    started TEXT NOT NULL,
+                    zone TEXT NOT NULL,
+                    description TEXT NOT NULL
+                );",
+            )
+            .map_err(StorageError::Sqlite)?;
+        }
+
+        // Set within the transaction so a crash part way leaves the database
+        // as it was
+        if version != SCHEMA_VERSION {
+            tx.pragma_update(None, "user_version", SCHEMA_VERSION)
+                .map_err(StorageError::Sqlite)?;
+        }
+
+        tx.commit().map_err(StorageError::Sqlite)
+    }
+
+    // Copies the current entrys and archive from another storage
+    pub fn import(&mut self, from: &dyn Storage) -> Result<(), StorageError> {
+        let mut entrys = Vec::new();
+        let mut batches = Vec::new();
+        let mut total_minutes = 0;
+
+        from.read_entrys(&mut entrys, &mut total_minutes)?;
+        from.read_archive(&mut batches, &mut total_minutes)?;
+        let timer = from.read_timer()?;
+
+        let tx = self.conn.transaction().map_err(StorageError::Sqlite)?;
+
+        for entry in &entrys {
+            insert_entry(&tx, entry, None).map_err(StorageError::Sqlite)?;
+        }
+
+        for batch in &batches {
+            insert_batch(&tx, batch).map_err(StorageError::Sqlite)?;
+        }
+
+        if let Some(timer) = &timer {
+            insert_timer(&tx, timer).map_err(StorageError::Sqlite)?;
+        }
+
+        tx.commit().map_err(StorageError::Sqlite)
+    }
+
+    fn query(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+        entrys: &mut Vec<Entry>,
+    ) -> rusqlite::Result<()> {
+        let mut statement = self.conn.prepare(sql)?;
+
+        for entry in statement.query_map(params, get_entry)? {
+            entrys.push(entry?);
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read_entrys(
+        &self,
+        entrys: &mut Vec<Entry>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        let start = entrys.len();
+
+        self.query(
+            &format!("SELECT {COLUMNS} FROM entrys WHERE batch IS NULL ORDER BY id"),
+            [],
+            entrys,
+        )
+        .map_err(StorageError::Sqlite)?;
+
+        *total_minutes += entrys[start..]
+            .iter()
+            .map(|entry| entry.minutes)
+            .sum::<i64>();
+        entrys.sort_by(Entry::by_date);
+
+        Ok(())
+    }
+
+    fn write_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        insert_entry(&self.conn, entry, None).map_err(StorageError::Sqlite)
+    }
+
+    fn update_entry(&mut self, entry: &Entry) -> Result<(), StorageError> {
+        let changed = self
+            .conn
+            .execute(
+                "UPDATE entrys SET date = ?1, description = ?2, start = ?3, end = ?4,
+                    end_date = ?5, zone = ?6, minutes = ?7, break_minutes = ?8,
+                    billed_minutes = ?9 WHERE uuid = ?10 AND batch IS NULL",
+                params![
+                    entry.date.format(DATE_FORMAT).to_string(),
+                    entry.description,
+                    time_text(entry.start),
+                    time_text(entry.end),
+                    entry.end_date.format(DATE_FORMAT).to_string(),
+                    entry.zone.name(),
+                    entry.minutes,
+                    entry.break_minutes,
+                    entry.billed_minutes,
+                    entry.id.to_string()
+                ],
+            )
+            .map_err(StorageError::Sqlite)?;
+
+        if changed == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn delete_entry(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let changed = self
+            .conn
+            .execute(
+                "DELETE FROM entrys WHERE uuid = ?1 AND batch IS NULL",
+                [id.to_string()],
+            )
+            .map_err(StorageError::Sqlite)?;
+
+        if changed == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    fn read_archive(
+        &self,
+        batches: &mut Vec<Batch>,
+        total_minutes: &mut i64,
+    ) -> Result<(), StorageError> {
+        let start = batches.len();
+
+        let mut statement = self
+            .conn
+            .prepare("SELECT id, archived_at, label, count, minutes, billed_minutes FROM batches ORDER BY id")
+            .map_err(StorageError::Sqlite)?;
+
+        for batch in statement
+            .query_map([], get_batch)
+            .map_err(StorageError::Sqlite)?
+        {
+            batches.push(batch.map_err(StorageError::Sqlite)?);
+        }
+
+        for batch in &mut batches[start..] {
+            self.query(
+                &format!("SELECT {COLUMNS} FROM entrys WHERE batch = ?1 ORDER BY id"),
+                [batch.id],
+                &mut batch.entrys,
+            )
+            .map_err(StorageError::Sqlite)?;
+
+            *total_minutes += batch.entrys.iter().map(|entry| entry.minutes).sum::<i64>();
+        }
+
+        Ok(())
+    }
+
+    fn archive_entrys(
+        &mut self,
+        entrys: &[Entry],
+        label: &str,
+        billed_minutes: i64,
+    ) -> Result<(), StorageError> {
+        let tx = self.conn.transaction().map_err(StorageError::Sqlite)?;
+
+        let id: u64 = tx
+            .query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM batches", [], |row| {
+                row.get(0)
+            })
+            .map_err(StorageError::Sqlite)?;
+
+        let batch = Batch::new(
+            id,
+            Some(Local::now().naive_local()),
+            label.to_string(),
+            Some(billed_minutes),
+            entrys.to_vec(),
+        );
+
+        tx.execute("DELETE FROM entrys WHERE batch IS NULL", [])
+            .map_err(StorageError::Sqlite)?;
+
+        insert_batch(&tx, &batch).map_err(StorageError::Sqlite)?;
+
+        tx.commit().map_err(StorageError::Sqlite)
+    }
+
+    fn read_timer(&self) -> Result<Option<Timer>, StorageError> {
+        self.conn
+            .query_row(
+                "SELECT uuid, started, zone, description FROM timer",
+                [],
+                get_timer,
+            )
+            .optional()
+            .map_err(StorageError::Sqlite)
+    }
+
+    fn write_timer(&mut self, timer: Option<&Timer>) -> Result<(), StorageError> {
+        let tx = self.conn.transaction().map_err(StorageError::Sqlite)?;
+
+        tx.execute("DELETE FROM timer", [])
+            .map_err(StorageError::Sqlite)?;
+
+        if let Some(timer) = timer {
+            insert_timer(&tx, timer).map_err(StorageError::Sqlite)?;
+        }
+
+        tx.commit().map_err(StorageError::Sqlite)
+    }
+
+    fn entrys_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        entrys: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        // Found through the index on date rather than reading every entry
+        self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM entrys WHERE date BETWEEN ?1 AND ?2 ORDER BY date DESC"
+            ),
+            params![
+                from.format(DATE_FORMAT).to_string(),
+                to.format(DATE_FORMAT).to_string()
+            ],
+            entrys,
+        )
+        .map_err(StorageError::Sqlite)
+    }
+}
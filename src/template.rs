@@ -0,0 +1,375 @@
+use std::fs::{create_dir_all, read};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::csv;
+use crate::form::EntryForm;
+
+// An entry that is logged often, applied from the input row. One that
+// repeats also makes drafts for the days it happens on.
+#[derive(Clone)]
+pub struct Template {
+    pub description: String,
+    // As they were typed, so they read the same in the form
+    pub start_time: String,
+    pub end_time: String,
+    pub end_days: u64,
+    pub duration: String,
+    pub break_time: String,
+    pub zone: String,
+    pub recurrence: Recurrence,
+}
+
+#[derive(Clone, Copy)]
+pub struct Recurrence {
+    pub repeat: Repeat,
+    // The days a weekly template is on, Monday first
+    pub days: [bool; 7],
+    // Weekly templates are on every this many weeks, counted from the week
+    // of `since`
+    pub every: u32,
+    // Nothing is made for days before this
+    pub since: NaiveDate,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Never,
+    Daily,
+    Weekdays,
+    Weekly,
+}
+
+impl Repeat {
+    pub const ALL: [Repeat; 4] = [
+        Repeat::Never,
+        Repeat::Daily,
+        Repeat::Weekdays,
+        Repeat::Weekly,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Repeat::Never => "never",
+            Repeat::Daily => "daily",
+            Repeat::Weekdays => "weekdays",
+            Repeat::Weekly => "weekly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Repeat> {
+        Repeat::ALL.into_iter().find(|repeat| repeat.name() == name)
+    }
+}
+
+impl Recurrence {
+    pub fn never(since: NaiveDate) -> Self {
+        Recurrence {
+            repeat: Repeat::Never,
+            days: [false; 7],
+            every: 1,
+            since,
+        }
+    }
+
+    pub fn on(&self, date: NaiveDate) -> bool {
+        if date < self.since {
+            return false;
+        }
+
+        match self.repeat {
+            Repeat::Never => false,
+            Repeat::Daily => true,
+            Repeat::Weekdays => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+            Repeat::Weekly => {
+                let weeks = (monday(date) - monday(self.since)).num_days() / 7;
+
+                self.days[date.weekday().num_days_from_monday() as usize]
+                    && weeks % i64::from(self.every.max(1)) == 0
+            }
+        }
+    }
+}
+
+fn monday(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+impl Template {
+    pub fn from_form(form: &EntryForm) -> Self {
+        Template {
+            description: form.description.trim().to_string(),
+            start_time: form.start_time.trim().to_string(),
+            end_time: form.end_time.trim().to_string(),
+            end_days: form.end_days,
+            duration: form.duration.trim().to_string(),
+            break_time: form.break_time.trim().to_string(),
+            zone: form.zone.trim().to_string(),
+            recurrence: Recurrence::never(form.date),
+        }
+    }
+
+    // What it would fill the input row with on `date`
+    pub fn form(&self, date: NaiveDate) -> EntryForm {
+        EntryForm {
+            description: self.description.clone(),
+            start_time: self.start_time.clone(),
+            end_time: self.end_time.clone(),
+            end_days: self.end_days,
+            duration: self.duration.clone(),
+            break_time: self.break_time.clone(),
+            zone: self.zone.clone(),
+            ..EntryForm::new(date)
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = if self.start_time.is_empty() && self.end_time.is_empty() {
+            format!("{} {}", self.description, self.duration)
+        } else {
+            format!("{} {}-{}", self.description, self.start_time, self.end_time)
+        };
+
+        if !self.break_time.is_empty() {
+            label.push_str(&format!(" ({} break)", self.break_time));
+        }
+
+        label
+    }
+}
+
+// Kept with the data so each data directory has its own
+fn templates_path(dir: &Path) -> PathBuf {
+    dir.join("templates.csv")
+}
+
+// None saved yet is no templates, anything it can't read is an error so the
+// file isn't saved over
+pub fn load(dir: &Path) -> Result<Vec<Template>, io::Error> {
+    let bytes = match read(templates_path(dir)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    csv::parse(&String::from_utf8_lossy(&bytes))
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            get_template(record).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("template {} can't be read", i + 1),
+                )
+            })
+        })
+        .collect()
+}
+
+pub fn save(dir: &Path, templates: &[Template]) -> Result<(), io::Error> {
+    create_dir_all(dir)?;
+
+    let records: Vec<_> = templates.iter().map(template_record).collect();
+
+    csv::replace_records(&templates_path(dir), &records)
+}
+
+fn template_record(template: &Template) -> Vec<String> {
+    let recurrence = &template.recurrence;
+
+    let days: Vec<String> = recurrence
+        .days
+        .iter()
+        .enumerate()
+        .filter(|(_, on)| **on)
+        .filter_map(|(i, _)| Weekday::try_from(i as u8).ok())
+        .map(|day| day.to_string())
+        .collect();
+
+    vec![
+        template.description.clone(),
+        template.start_time.clone(),
+        template.end_time.clone(),
+        recurrence.repeat.name().to_string(),
+        days.join(" "),
+        recurrence.every.to_string(),
+        recurrence.since.format("%Y-%m-%d").to_string(),
+        template.end_days.to_string(),
+        template.duration.clone(),
+        template.break_time.clone(),
+        template.zone.clone(),
+    ]
+}
+
+fn get_template(record: &[String]) -> Option<Template> {
+    let [description, start_time, end_time, repeat, days, every, since, rest @ ..] = record else {
+        return None;
+    };
+
+    let [end_days, duration, break_time, zone] = rest else {
+        return None;
+    };
+
+    let mut recurrence = Recurrence {
+        repeat: Repeat::from_name(repeat)?,
+        every: every.parse().ok()?,
+        ..Recurrence::never(NaiveDate::parse_from_str(since, "%Y-%m-%d").ok()?)
+    };
+
+    for day in days.split_whitespace() {
+        let day: Weekday = day.parse().ok()?;
+        recurrence.days[day.num_days_from_monday() as usize] = true;
+    }
+
+    Some(Template {
+        description: description.clone(),
+        start_time: start_time.clone(),
+        end_time: end_time.clone(),
+        end_days: end_days.parse().ok()?,
+        duration: duration.clone(),
+        break_time: break_time.clone(),
+        zone: zone.clone(),
+        recurrence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TimeDefaults;
+
+    #[test]
+    fn recurs_on_days() {
+        // A Monday
+        let since = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let day = |offset: u64| since + Days::new(offset);
+
+        let recurrence = |repeat, days: &[usize], every| {
+            let mut recurrence = Recurrence {
+                repeat,
+                every,
+                ..Recurrence::never(since)
+            };
+
+            for &day in days {
+                recurrence.days[day] = true;
+            }
+
+            recurrence
+        };
+
+        let cases = [
+            (recurrence(Repeat::Never, &[], 1), day(0), false),
+            (recurrence(Repeat::Daily, &[], 1), day(6), true),
+            (
+                recurrence(Repeat::Daily, &[], 1),
+                since.pred_opt().unwrap(),
+                false,
+            ),
+            (recurrence(Repeat::Weekdays, &[], 1), day(4), true),
+            (recurrence(Repeat::Weekdays, &[], 1), day(5), false),
+            (recurrence(Repeat::Weekly, &[0, 2], 1), day(2), true),
+            (recurrence(Repeat::Weekly, &[0, 2], 1), day(3), false),
+            (recurrence(Repeat::Weekly, &[0, 2], 1), day(9), true),
+            (recurrence(Repeat::Weekly, &[0], 2), day(7), false),
+            (recurrence(Repeat::Weekly, &[0], 2), day(14), true),
+            // Counted in weeks from Monday, not from `since`
+            (recurrence(Repeat::Weekly, &[6], 2), day(6), true),
+            (recurrence(Repeat::Weekly, &[0], 0), day(7), true),
+        ];
+
+        for (recurrence, date, expected) in cases {
+            assert_eq!(
+                recurrence.on(date),
+                expected,
+                "{} every {} on {date}",
+                recurrence.repeat.name(),
+                recurrence.every
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_the_whole_form() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+
+        let forms = [
+            EntryForm {
+                description: "Shift".to_string(),
+                start_time: "8am".to_string(),
+                end_time: "4:30pm".to_string(),
+                break_time: "30m".to_string(),
+                ..EntryForm::new(date)
+            },
+            EntryForm {
+                description: "Standup".to_string(),
+                duration: "15m".to_string(),
+                ..EntryForm::new(date)
+            },
+            EntryForm {
+                description: "Night".to_string(),
+                start_time: "22:00".to_string(),
+                end_time: "06:00".to_string(),
+                end_days: 1,
+                zone: "Europe/Berlin".to_string(),
+                ..EntryForm::new(date)
+            },
+        ];
+
+        let expected = [
+            (480, 30, None),
+            (15, 0, None),
+            (480, 0, Some("Europe/Berlin")),
+        ];
+
+        for (form, (minutes, break_minutes, zone)) in forms.iter().zip(expected) {
+            // Through the file and back
+            let template = get_template(&template_record(&Template::from_form(form))).unwrap();
+            let later = date + Days::new(7);
+
+            let entry = template
+                .form(later)
+                .entry(TimeDefaults::default())
+                .ok()
+                .unwrap();
+
+            assert_eq!(entry.date, later, "{}", form.description);
+            assert_eq!(entry.minutes, minutes, "{}", form.description);
+            assert_eq!(entry.break_minutes, break_minutes, "{}", form.description);
+
+            if let Some(zone) = zone {
+                assert_eq!(entry.zone.name(), zone);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_a_file_it_cant_read() {
+        let dir = std::env::temp_dir().join(format!("hours-test-{}", uuid::Uuid::new_v4()));
+
+        // Nothing saved yet
+        assert!(load(&dir).unwrap().is_empty());
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let template = Template::from_form(&EntryForm {
+            description: "Standup".to_string(),
+            duration: "15m".to_string(),
+            ..EntryForm::new(date)
+        });
+        save(&dir, &[template.clone(), template]).unwrap();
+        assert_eq!(load(&dir).unwrap().len(), 2);
+
+        // One of them cut short
+        let path = templates_path(&dir);
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (first, _) = text.rsplit_once(',').unwrap();
+        std::fs::write(&path, format!("{first}\r\n")).unwrap();
+
+        let kind = load(&dir).err().map(|e| e.kind());
+        assert_eq!(kind, Some(io::ErrorKind::InvalidData));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
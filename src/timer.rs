@@ -0,0 +1,62 @@
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::entry::Entry;
+use crate::utils::minutes_in;
+
+// A running entry, saved as soon as it starts so it outlasts a restart
+#[derive(Clone)]
+pub struct Timer {
+    // Becomes the id of the entry it is stopped into
+    pub id: Uuid,
+    pub started: DateTime<Utc>,
+    // The zone it was started in, for the times of its entry
+    pub zone: Tz,
+    pub description: String,
+}
+
+impl Timer {
+    pub fn new(zone: Tz, description: String) -> Self {
+        Timer {
+            id: Uuid::new_v4(),
+            started: Utc::now(),
+            zone,
+            description,
+        }
+    }
+
+    // Whole seconds since it started
+    pub fn elapsed(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.started).num_seconds().max(0)
+    }
+
+    // The entry from when it started until `now`, to the minute. None if it
+    // hasn't run for a minute yet.
+    pub fn entry(&self, now: DateTime<Utc>) -> Option<Entry> {
+        let minute = |at: DateTime<Utc>| {
+            let at = at.with_timezone(&self.zone).naive_local();
+            at.with_second(0)?.with_nanosecond(0)
+        };
+
+        let (start, end) = (minute(self.started)?, minute(now)?);
+        let minutes = minutes_in(self.zone, start, end)?;
+
+        if minutes < 1 {
+            return None;
+        }
+
+        Some(Entry {
+            id: self.id,
+            ..Entry::new(
+                start.date(),
+                self.description.clone(),
+                Some(start.time()),
+                Some(end.time()),
+                end.date(),
+                self.zone,
+                minutes,
+            )
+        })
+    }
+}
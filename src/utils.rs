@@ -1,224 +1,659 @@
-use std::fs::{create_dir, File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-
-use dirs_next::config_dir;
+use std::fmt;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+
+// How times that could be morning or afternoon are read
+#[derive(Clone, Copy, Default)]
+pub struct TimeDefaults {
+    // Hours below this written without am/pm or a leading zero are taken as
+    // the afternoon, so with 7 "5:30" is 17:30 but "9" is still 09:00. 0 reads
+    // every time as written.
+    pub pm_before: u32,
+}
 
-use crate::entry::Entry;
+// Minutes from the start to the end, an end before the start is the next day
+pub fn minutes_between(start: NaiveTime, end: NaiveTime) -> i64 {
+    let mut minutes = minutes(end) - minutes(start);
 
-pub fn write_to_archive(entrys: &[Entry]) -> Result<(), std::io::Error> {
-    let path = config_dir();
+    if minutes < 0 {
+        minutes += 24 * 60;
+    }
 
-    let mut path = if let Some(path) = path {
-        path
-    } else {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Config dir not found",
-        ));
-    };
+    minutes
+}
 
-    path.push("hours");
-    path.push("archive.csv");
+// The zone the computer was set to when Hours started, or UTC when it can't
+// be told
+pub fn local_zone() -> Tz {
+    static ZONE: OnceLock<Tz> = OnceLock::new();
+
+    *ZONE.get_or_init(|| {
+        iana_time_zone::get_timezone()
+            .ok()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or(Tz::UTC)
+    })
+}
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .create(true)
-        .open(path)?;
+// The instant a wall clock time in a zone is at. A time repeated when the
+// clocks go back is the first of the two, and a time skipped when they go
+// forward is taken as the clock would have read without the change.
+pub fn instant(zone: Tz, time: NaiveDateTime) -> Option<DateTime<Tz>> {
+    zone.from_local_datetime(&time).earliest().or_else(|| {
+        let before = zone
+            .from_local_datetime(&(time - Duration::hours(3)))
+            .earliest()?;
+        let offset = before.naive_local() - before.naive_utc();
+
+        Some(zone.from_utc_datetime(&(time - offset)))
+    })
+}
 
-    for entry in entrys {
-        writeln!(
-            file,
-            "{},{},{},{},{}",
-            entry.date, entry.description, entry.start, entry.end, entry.hours
-        )?;
-    }
+// Minutes that really passed between two wall clock times in a zone, so a
+// change to or from daylight saving time is counted
+pub fn minutes_in(zone: Tz, from: NaiveDateTime, to: NaiveDateTime) -> Option<i64> {
+    Some((instant(zone, to)? - instant(zone, from)?).num_minutes())
+}
 
-    writeln!(file, "-",)?;
+// Minutes as hours for showing, to two decimal places at most
+pub fn format_hours(minutes: i64) -> String {
+    ((minutes as f64 / 60.0 * 100.0).round() / 100.0).to_string()
+}
 
-    Ok(())
+// Seconds as h:mm:ss, for a running timer
+pub fn format_elapsed(seconds: i64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
 }
 
-pub fn read_archive(entrys: &mut Vec<Entry>, total_hours: &mut f64) -> Result<(), std::io::Error> {
-    let mut path = if let Some(path) = config_dir() {
-        path
-    } else {
-        return Ok(());
-    };
+// Reads a length of time worked as minutes, rounded to the nearest minute.
+// Case and spaces don't matter.
+//
+//   duration = hours ":" minute | [number "h"] [digits ["m"]] | digits "m" | number
+//   number   = digits ["." digits]
+//   minute   = 2 digits
+//
+// So "2h30m", "2h 30", "2h", "90m", "2:30", "1.75h" and "1.75" all work. A
+// bare number is hours. Nothing comes of a duration of zero.
+pub fn parse_duration(duration: &str) -> Result<i64, DurationError> {
+    let duration: String = duration.to_ascii_lowercase().split_whitespace().collect();
+
+    if duration.is_empty() {
+        return Err(DurationError::Empty);
+    }
 
-    path.push("hours");
+    let number = |number: &str| parse_number(number).ok_or(DurationError::Unreadable);
 
-    if !path.exists() {
-        create_dir(&path)?;
-    }
+    let minutes = if let Some((hours, minute)) = duration.split_once(':') {
+        if hours.contains('.') {
+            return Err(DurationError::Unreadable);
+        }
 
-    path.push("archive.csv");
+        if minute.len() != 2 {
+            return Err(DurationError::BadMinutes);
+        }
 
-    if path.exists() {
-        let file = File::open(path)?;
-        let file = BufReader::new(file);
+        let minute = number(minute)?;
 
-        for line in file.lines().flatten() {
-            if line == "-" {
-                // Empty entry for a break
-                entrys.push(Entry::new(
-                    String::new(),
-                    String::new(),
-                    String::new(),
-                    String::new(),
-                    0.0,
-                ));
-            } else if let Some(entry) = get_entry(line) {
-                *total_hours += entry.hours;
-                entrys.push(entry);
-            }
+        if minute >= 60.0 {
+            return Err(DurationError::BadMinutes);
         }
-    }
 
-    Ok(())
-}
+        number(hours)? * 60.0 + minute
+    } else if let Some((hours, rest)) = duration.split_once('h') {
+        let rest = rest.strip_suffix('m').unwrap_or(rest);
+
+        let minutes = if rest.is_empty() {
+            0.0
+        } else if rest.contains('.') {
+            return Err(DurationError::BadMinutes);
+        } else {
+            number(rest)?
+        };
+
+        number(hours)? * 60.0 + minutes
+    } else if let Some(minutes) = duration.strip_suffix('m') {
+        number(minutes)?
+    } else {
+        number(&duration)? * 60.0
+    };
 
-pub fn write_entry(entry: &Entry) {
-    let path = config_dir();
+    let minutes = minutes.round() as i64;
 
-    if let Some(mut path) = path {
-        path.push("hours");
-        path.push("entrys.csv");
+    if minutes <= 0 {
+        return Err(DurationError::Zero);
+    }
 
-        let file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .create(true)
-            .open(path);
+    Ok(minutes)
+}
 
-        if let Ok(mut file) = file {
-            if let Err(e) = writeln!(
-                file,
-                "{},{},{},{},{}",
-                entry.date, entry.description, entry.start, entry.end, entry.hours
-            ) {
-                eprintln!("Couldn't write to file: {e}");
-            }
-        }
+// Why a duration couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurationError {
+    Empty,
+    // Zero, or less than half a minute
+    Zero,
+    BadMinutes,
+    Unreadable,
+}
+
+impl fmt::Display for DurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DurationError::Empty => "Needs a duration",
+            DurationError::Zero => "Has to be longer than that",
+            DurationError::BadMinutes => "Minutes are two digits from 00 to 59",
+            DurationError::Unreadable => "Not a duration, try 2h30m or 2:30",
+        })
     }
 }
 
-pub fn wipe_entrys() {
-    let path = config_dir();
+// Plain digits with an optional decimal part, unlike `str::parse` which also
+// takes signs, exponents and "inf"
+fn parse_number(number: &str) -> Option<f64> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
 
-    if let Some(mut path) = path {
-        path.push("hours");
-        path.push("entrys.csv");
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
 
-        let _file = OpenOptions::new().truncate(true).open(path);
+    if whole.is_empty() || !digits(whole) || !digits(fraction) || number.ends_with('.') {
+        return None;
     }
+
+    number.parse().ok()
 }
 
-pub fn get_entry(line: String) -> Option<Entry> {
-    let mut entry = line.splitn(5, ',');
+fn minutes(time: NaiveTime) -> i64 {
+    (time.hour() * 60 + time.minute()).into()
+}
 
-    Some(Entry::new(
-        entry.next()?.to_string(),
-        entry.next()?.to_string(),
-        entry.next()?.to_string(),
-        entry.next()?.to_string(),
-        entry.next()?.parse::<f64>().ok()?,
-    ))
+enum Meridiem {
+    Am,
+    Pm,
 }
 
-pub fn read_entrys(entrys: &mut Vec<Entry>, total_hours: &mut f64) -> Result<(), std::io::Error> {
-    let mut path = if let Some(path) = config_dir() {
-        path
-    } else {
-        return Ok(());
-    };
+// Reads a time of day. Case and surrounding spaces don't matter.
+//
+//   time     = "noon" | "midday" | "midnight" | clock [" "] [meridiem]
+//   clock    = hour [separator minute] | hour separator | compact
+//   hour     = 1 or 2 digits
+//   minute   = 2 digits
+//   separator = ":" | "." | "h"
+//   compact  = 3 or 4 digits, the last 2 are the minute ("930", "0930", "1330")
+//   meridiem = "am" | "pm" | "a" | "p" | "a.m." | "p.m."
+//
+// With a meridiem the hour has to be 1 to 12, 12am is midnight and 12pm is
+// noon. Without one the hour is 0 to 23, and an hour from 1 to 11 written
+// without a leading zero is moved to the afternoon if it is below
+// `defaults.pm_before`. Only a separator of "h" may leave out the minute.
+pub fn parse_time(time: &str, defaults: TimeDefaults) -> Result<NaiveTime, TimeError> {
+    let time = time.trim().to_ascii_lowercase();
+
+    match time.as_str() {
+        "" => return Err(TimeError::Empty),
+        "noon" | "midday" => return NaiveTime::from_hms_opt(12, 0, 0).ok_or(TimeError::Unreadable),
+        "midnight" => return Ok(NaiveTime::MIN),
+        _ => {}
+    }
+
+    let (clock, meridiem) = split_meridiem(&time);
+    let (hour_digits, minute_digits) = split_clock(clock.trim_end())?;
 
-    path.push("hours");
+    let mut hour: u32 = hour_digits.parse().map_err(|_| TimeError::Unreadable)?;
+    let minute: u32 = minute_digits.parse().map_err(|_| TimeError::Unreadable)?;
 
-    if !path.exists() {
-        create_dir(&path)?;
+    if minute > 59 {
+        return Err(TimeError::BadMinutes);
     }
 
-    path.push("entrys.csv");
+    match meridiem {
+        Some(meridiem) => {
+            if !(1..=12).contains(&hour) {
+                return Err(TimeError::BadHour);
+            }
+
+            hour = match meridiem {
+                Meridiem::Am => hour % 12,
+                Meridiem::Pm => hour % 12 + 12,
+            };
+        }
+        None => {
+            if hour > 23 {
+                return Err(TimeError::BadHour);
+            }
 
-    if path.exists() {
-        let file = File::open(path)?;
-        let file = BufReader::new(file);
+            let padded = hour_digits.starts_with('0');
 
-        for line in file.lines().flatten() {
-            if let Some(entry) = get_entry(line) {
-                *total_hours += entry.hours;
-                entrys.push(entry);
+            if !padded && (1..12).contains(&hour) && hour < defaults.pm_before {
+                hour += 12;
             }
         }
+    }
+
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or(TimeError::Unreadable)
+}
+
+// Why a time couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeError {
+    Empty,
+    BadHour,
+    BadMinutes,
+    Unreadable,
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TimeError::Empty => "Needs a time",
+            TimeError::BadHour => "Hours are 0 to 23, or 1 to 12 with am/pm",
+            TimeError::BadMinutes => "Minutes are two digits from 00 to 59",
+            TimeError::Unreadable => "Not a time, try 9:30am or 17:30",
+        })
+    }
+}
+
+// Whether the time was written with am or pm
+pub fn has_meridiem(time: &str) -> bool {
+    split_meridiem(time.trim().to_ascii_lowercase().as_str())
+        .1
+        .is_some()
+}
 
-        entrys.sort();
+fn split_meridiem(time: &str) -> (&str, Option<Meridiem>) {
+    let suffixes = [
+        ("a.m.", Meridiem::Am),
+        ("p.m.", Meridiem::Pm),
+        ("am", Meridiem::Am),
+        ("pm", Meridiem::Pm),
+        ("a", Meridiem::Am),
+        ("p", Meridiem::Pm),
+    ];
+
+    for (suffix, meridiem) in suffixes {
+        if let Some(clock) = time.strip_suffix(suffix) {
+            return (clock, Some(meridiem));
+        }
     }
 
-    Ok(())
+    (time, None)
 }
 
-pub fn parse_difference(start: &str, end: &str) -> Option<f64> {
-    let start = parse_time(start);
-    let end = parse_time(end);
+// The hour and minute digits, the minute is "00" when left out
+fn split_clock(clock: &str) -> Result<(&str, &str), TimeError> {
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+
+    // Splitting by length below would land inside a wider character
+    if !clock.is_ascii() {
+        return Err(TimeError::Unreadable);
+    }
 
-    if let (Some(start), Some(end)) = (start, end) {
-        let mut difference = end - start;
+    let (hour, minute) = match clock.find([':', '.', 'h']) {
+        Some(i) => {
+            let (hour, minute) = (&clock[..i], &clock[i + 1..]);
 
-        if difference < 0.0 {
-            difference += 24.0;
+            if minute.is_empty() && clock[i..].starts_with('h') {
+                (hour, "00")
+            } else {
+                (hour, minute)
+            }
         }
+        None => match clock.len() {
+            1 | 2 => (clock, "00"),
+            3 | 4 => clock.split_at(clock.len() - 2),
+            _ => return Err(TimeError::Unreadable),
+        },
+    };
 
-        return Some(difference);
+    if !all_digits(hour) || !minute.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TimeError::Unreadable);
     }
 
-    None
+    if hour.len() > 2 {
+        return Err(TimeError::BadHour);
+    }
+
+    if minute.len() != 2 {
+        return Err(TimeError::BadMinutes);
+    }
+
+    Ok((hour, minute))
 }
 
-fn parse_time(time: &str) -> Option<f64> {
-    let bytes = time.bytes();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut acc = 0.0;
-    let mut hours = 0.0;
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
 
-    for b in bytes {
-        if b == b':' {
-            hours = acc;
-            acc = 0.0;
-            continue;
+    #[test]
+    fn parses_times() {
+        let cases = [
+            // 12 hour clock
+            ("1:30pm", time(13, 30)),
+            ("1:30 pm", time(13, 30)),
+            ("1:30PM", time(13, 30)),
+            ("10:30am", time(10, 30)),
+            ("9am", time(9, 0)),
+            ("9 am", time(9, 0)),
+            ("9a", time(9, 0)),
+            ("9p", time(21, 0)),
+            ("9.30p", time(21, 30)),
+            ("9:30 p.m.", time(21, 30)),
+            ("7 a.m.", time(7, 0)),
+            ("12am", time(0, 0)),
+            ("12:15am", time(0, 15)),
+            ("12pm", time(12, 0)),
+            ("12:45pm", time(12, 45)),
+            // 24 hour clock
+            ("13:30", time(13, 30)),
+            ("0:00", time(0, 0)),
+            ("00:00", time(0, 0)),
+            ("23:59", time(23, 59)),
+            ("09:05", time(9, 5)),
+            ("1:30", time(1, 30)),
+            // Other separators
+            ("9.30", time(9, 30)),
+            ("17.45", time(17, 45)),
+            ("9h30", time(9, 30)),
+            ("18h", time(18, 0)),
+            ("9h", time(9, 0)),
+            // Compact
+            ("930", time(9, 30)),
+            ("0930", time(9, 30)),
+            ("1330", time(13, 30)),
+            ("0000", time(0, 0)),
+            ("2359", time(23, 59)),
+            ("930pm", time(21, 30)),
+            // Bare hours
+            ("9", time(9, 0)),
+            ("09", time(9, 0)),
+            ("0", time(0, 0)),
+            ("17", time(17, 0)),
+            // Words
+            ("noon", time(12, 0)),
+            ("Noon", time(12, 0)),
+            ("midday", time(12, 0)),
+            ("midnight", time(0, 0)),
+            // Spaces around
+            ("  13:30 ", time(13, 30)),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_time(input, TimeDefaults::default()).ok(),
+                expected,
+                "{input:?}"
+            );
         }
+    }
 
-        if !b.is_ascii_digit() {
-            break;
+    #[test]
+    fn rejects_invalid_times() {
+        let cases = [
+            "", " ", "pm", "am", "abc", "24:00", "25", "13pm", "0am", "0:30pm", "9:60", "9:5",
+            "9:305", "9:", "9.", ":30", "h30", "123:00", "12345", "9:30x", "9 30", "9:3o", "-9:30",
+            "noonish", "€", "é1", "9€", "１２", "9:3€",
+        ];
+
+        for input in cases {
+            assert_eq!(
+                parse_time(input, TimeDefaults::default()).ok(),
+                None,
+                "{input:?}"
+            );
         }
+    }
 
-        acc *= 10.0;
-        acc += (b - b'0') as f64;
+    #[test]
+    fn explains_invalid_times() {
+        let cases = [
+            ("", TimeError::Empty),
+            ("  ", TimeError::Empty),
+            ("24:00", TimeError::BadHour),
+            ("13pm", TimeError::BadHour),
+            ("0am", TimeError::BadHour),
+            ("123:00", TimeError::BadHour),
+            ("9:60", TimeError::BadMinutes),
+            ("9:5", TimeError::BadMinutes),
+            ("9:", TimeError::BadMinutes),
+            ("abc", TimeError::Unreadable),
+            ("9:3o", TimeError::Unreadable),
+            ("12345", TimeError::Unreadable),
+            ("pm", TimeError::Unreadable),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_time(input, TimeDefaults::default()),
+                Err(expected),
+                "{input:?}"
+            );
+        }
     }
 
-    // There is no such thing as hour 0 so if hours is zeros its cause there was no :
-    if hours == 0.0 {
-        hours = acc;
-        acc = 0.0;
+    #[test]
+    fn bare_hours_follow_defaults() {
+        let defaults = TimeDefaults { pm_before: 7 };
+
+        let cases = [
+            ("5", time(17, 0)),
+            ("1", time(13, 0)),
+            ("6:30", time(18, 30)),
+            ("530", time(17, 30)),
+            ("6h", time(18, 0)),
+            // At or after the cut off stays in the morning
+            ("7", time(7, 0)),
+            ("9:30", time(9, 30)),
+            ("11", time(11, 0)),
+            // A leading zero, a meridiem or a 24 hour time is never moved
+            ("05", time(5, 0)),
+            ("0530", time(5, 30)),
+            ("5am", time(5, 0)),
+            ("5pm", time(17, 0)),
+            ("0", time(0, 0)),
+            ("12", time(12, 0)),
+            ("15", time(15, 0)),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_time(input, defaults).ok(), expected, "{input:?}");
+        }
     }
 
-    if !(1.0..=12.0).contains(&hours) {
-        return None;
+    #[test]
+    fn differences() {
+        let defaults = TimeDefaults::default();
+
+        let cases = [
+            ("9am", "5pm", 480),
+            ("9:00", "17:30", 510),
+            ("1:30pm", "10:30pm", 540),
+            ("9:10am", "9:20am", 10),
+            ("9:00", "9:00", 0),
+            // Past midnight
+            ("22:00", "2:00", 240),
+            ("11pm", "midnight", 60),
+        ];
+
+        for (start, end, expected) in cases {
+            let start_time = parse_time(start, defaults).unwrap();
+            let end_time = parse_time(end, defaults).unwrap();
+
+            assert_eq!(
+                minutes_between(start_time, end_time),
+                expected,
+                "{start:?} to {end:?}"
+            );
+        }
     }
 
-    if !(0.0..=59.0).contains(&acc) {
-        return None;
+    #[test]
+    fn counts_real_minutes() {
+        let at = |date: &str, time: &str| {
+            NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M").unwrap()
+        };
+
+        let sydney: Tz = "Australia/Sydney".parse().unwrap();
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        let cases = [
+            (
+                Tz::UTC,
+                at("2024-03-10", "22:00"),
+                at("2024-03-11", "06:00"),
+                480,
+            ),
+            // New York springs forward at 2am on the 10th of March 2024
+            (
+                new_york,
+                at("2024-03-09", "22:00"),
+                at("2024-03-10", "06:00"),
+                420,
+            ),
+            (
+                new_york,
+                at("2024-03-10", "09:00"),
+                at("2024-03-10", "17:00"),
+                480,
+            ),
+            // and falls back on the 3rd of November
+            (
+                new_york,
+                at("2024-11-02", "22:00"),
+                at("2024-11-03", "06:00"),
+                540,
+            ),
+            // Skipped times read as if the clocks hadn't changed
+            (
+                new_york,
+                at("2024-03-10", "02:30"),
+                at("2024-03-10", "04:00"),
+                30,
+            ),
+            // Sydney falls back at 3am on the 7th of April 2024
+            (
+                sydney,
+                at("2024-04-06", "20:00"),
+                at("2024-04-07", "04:00"),
+                540,
+            ),
+            // A 30 hour span
+            (
+                Tz::UTC,
+                at("2024-01-01", "06:00"),
+                at("2024-01-02", "12:00"),
+                1800,
+            ),
+        ];
+
+        for (zone, from, to, expected) in cases {
+            assert_eq!(
+                minutes_in(zone, from, to),
+                Some(expected),
+                "{zone} {from} to {to}"
+            );
+        }
     }
 
-    if time.ends_with("pm") && hours.trunc() != 12.0 {
-        hours += 12.0;
+    #[test]
+    fn formats_hours() {
+        let cases = [
+            (0, "0"),
+            (60, "1"),
+            (90, "1.5"),
+            (20, "0.33"),
+            (40, "0.67"),
+            (200, "3.33"),
+            (1560, "26"),
+        ];
+
+        for (minutes, expected) in cases {
+            assert_eq!(format_hours(minutes), expected, "{minutes}");
+        }
     }
 
-    let hours = hours + (((acc / 60.0) * 100.0).round() / 100.0);
+    #[test]
+    fn formats_elapsed() {
+        let cases = [
+            (0, "0:00:00"),
+            (59, "0:00:59"),
+            (61, "0:01:01"),
+            (3600, "1:00:00"),
+            (45296, "12:34:56"),
+            (90000, "25:00:00"),
+        ];
+
+        for (seconds, expected) in cases {
+            assert_eq!(format_elapsed(seconds), expected, "{seconds}");
+        }
+    }
 
-    if hours == 0.0 {
-        return None;
+    #[test]
+    fn parses_durations() {
+        let cases = [
+            ("2h30m", Some(150)),
+            ("2h 30m", Some(150)),
+            ("2H30M", Some(150)),
+            ("2h30", Some(150)),
+            ("2h", Some(120)),
+            ("2h0m", Some(120)),
+            ("1h5m", Some(65)),
+            ("90m", Some(90)),
+            ("90 m", Some(90)),
+            ("45m", Some(45)),
+            ("20m", Some(20)),
+            ("2:30", Some(150)),
+            ("0:45", Some(45)),
+            ("10:05", Some(605)),
+            ("1.75h", Some(105)),
+            ("1.75", Some(105)),
+            ("3", Some(180)),
+            (" 8 ", Some(480)),
+            ("0.5", Some(30)),
+            ("26h", Some(1560)),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_duration(input).ok(), expected, "{input:?}");
+        }
     }
 
-    Some(hours)
+    #[test]
+    fn explains_invalid_durations() {
+        let cases = [
+            ("", DurationError::Empty),
+            ("0", DurationError::Zero),
+            ("0:00", DurationError::Zero),
+            ("2:3", DurationError::BadMinutes),
+            ("2:60", DurationError::BadMinutes),
+            ("2h30.5m", DurationError::BadMinutes),
+            ("abc", DurationError::Unreadable),
+            ("-1", DurationError::Unreadable),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_duration(input), Err(expected), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        let cases = [
+            "", "h", "m", "hm", "0", "0h", "0.001", "0m", "0:00", "-1", "+1", "1e2", "inf", "nan",
+            "1.", ".5", "2:3", "2:60", "2:300", "1.5:30", "2h30.5m", "2h30s", "2m30", "abc",
+            "2 hours", "1,5",
+        ];
+
+        for input in cases {
+            assert_eq!(parse_duration(input).ok(), None, "{input:?}");
+        }
+    }
 }